@@ -0,0 +1,156 @@
+//! What's drawn behind the strokes: a solid color or some sort of paper.
+//!
+//! Grids are laid out in world pixels (a pixel of the canvas at zoom `1.0`)
+//! so they pan & zoom right along w/ the strokes. `background.f.glsl` does
+//! the same math as `coverage` does here, one pixel at a time.
+
+/// Distance between the lines of a grid (or its dots) in world pixels
+pub static GRID_PX: f32 = 32.0;
+
+/// Every this many lines is a major one
+pub static MAJOR_EVERY: f32 = 4.0;
+
+/// Radius of the dots in a dot grid, in pixels on the screen
+pub static DOT_PX: f32 = 1.5;
+
+/// Lines closer together than this (on the screen) fade out, so zooming out
+/// doesn't turn them into mush. They're gone by half of it.
+pub static MIN_SPACING_PX: f32 = 8.0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Background {
+    Solid,
+    Dots,
+
+    /// graph paper, w/ major & minor lines
+    Grid,
+
+    /// triangles, w/ lines running straight up & down plus 30 degrees off of level
+    Isometric,
+}
+
+static NAMES: &[(Background, &str)] = &[
+    (Background::Solid,     "solid"),
+    (Background::Dots,      "dots"),
+    (Background::Grid,      "grid"),
+    (Background::Isometric, "isometric"),
+];
+
+impl Background {
+    pub fn name(&self) -> &'static str {
+        NAMES.iter()
+            .find(|&&(background, _)| background == *self)
+            .map(|&(_, name)| name)
+            .expect("background is missing from the name table")
+    }
+
+    pub fn from_name(name: &str) -> Option<Background> {
+        NAMES.iter()
+            .find(|&&(_, known)| known.eq_ignore_ascii_case(name))
+            .map(|&(background, _)| background)
+    }
+
+    /// The next style, for cycling through them all from the keyboard
+    pub fn next(&self) -> Background {
+        let idx = NAMES.iter().position(|&(background, _)| background == *self)
+            .expect("background is missing from the name table");
+
+        NAMES[(idx + 1) % NAMES.len()].0
+    }
+
+    /// Passed to `background.f.glsl` as `style`
+    pub fn shader_style(&self) -> i32 {
+        match *self {
+            Background::Solid     => 0,
+            Background::Dots      => 1,
+            Background::Grid      => 2,
+            Background::Isometric => 3,
+        }
+    }
+}
+
+/// How far (in world pixels) `pos` is from the nearest of a family of lines
+/// `spacing` apart, which run across `normal`.
+fn line_distance(pos: [f32; 2], normal: [f32; 2], spacing: f32) -> f32 {
+    let t = (pos[0] * normal[0] + pos[1] * normal[1]) / spacing;
+    (t - (t + 0.5).floor()).abs() * spacing
+}
+
+/// `1.0` for lines which are far enough apart on the screen, fading to `0.0`
+fn fade(spacing_px: f32) -> f32 {
+    let half = MIN_SPACING_PX / 2.0;
+    ((spacing_px - half) / half).clamp(0.0, 1.0)
+}
+
+/// How much of the pixel at `pos` is covered by a grid w/ its lines (or
+/// dots) `spacing` world pixels apart.
+fn coverage_at(background: Background, pos: [f32; 2], zoom: f32, spacing: f32) -> f32 {
+    // NOTE: lines are a pixel wide on the screen no matter the zoom
+    let line = |normal: [f32; 2], spacing: f32| (1.0 - line_distance(pos, normal, spacing) * zoom).clamp(0.0, 1.0);
+
+    match background {
+        Background::Solid => 0.0,
+
+        Background::Dots => {
+            let dx = line_distance(pos, [1.0, 0.0], spacing);
+            let dy = line_distance(pos, [0.0, 1.0], spacing);
+            (DOT_PX + 0.5 - dx.hypot(dy) * zoom).clamp(0.0, 1.0)
+        },
+
+        Background::Grid => line([1.0, 0.0], spacing).max(line([0.0, 1.0], spacing)),
+
+        // NOTE: the lines are closer together than the sides of the triangles
+        Background::Isometric => {
+            let height = spacing * 3f32.sqrt() / 2.0;
+            line([1.0, 0.0], height)
+                .max(line([0.5, 0.866_025_4], height))
+                .max(line([-0.5, 0.866_025_4], height))
+        },
+    }
+}
+
+/// How much of the pixel at `pos` (in world pixels) is covered by the minor
+/// & major lines (or dots) of the grid, w/ `zoom` screen pixels per world pixel.
+pub fn coverage(background: Background, pos: [f32; 2], zoom: f32) -> [f32; 2] {
+    let zoom  = zoom.abs();
+    let major = GRID_PX * MAJOR_EVERY;
+
+    [coverage_at(background, pos, zoom, GRID_PX) * fade(GRID_PX * zoom),
+     coverage_at(background, pos, zoom, major)   * fade(major * zoom)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_cover_the_grid() {
+        assert_eq!(coverage(Background::Grid, [GRID_PX, 5.0], 1.0), [1.0, 0.0]);
+        assert_eq!(coverage(Background::Grid, [GRID_PX * MAJOR_EVERY, 5.0], 1.0), [1.0, 1.0]);
+        assert_eq!(coverage(Background::Grid, [GRID_PX / 2.0, 5.0], 1.0), [0.0, 0.0]);
+
+        // dots only show up where the lines would cross
+        assert_eq!(coverage(Background::Dots, [GRID_PX, GRID_PX], 1.0)[0], 1.0);
+        assert_eq!(coverage(Background::Dots, [GRID_PX, 5.0], 1.0)[0], 0.0);
+        assert_eq!(coverage(Background::Solid, [0.0, 0.0], 1.0), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn dense_lines_fade_out() {
+        let zoom = MIN_SPACING_PX / 2.0 / GRID_PX;
+        assert_eq!(coverage(Background::Grid, [0.0, 0.0], zoom), [0.0, 1.0]);
+        assert_eq!(coverage(Background::Grid, [0.0, 0.0], 1.0), [1.0, 1.0]);
+    }
+
+    #[test]
+    fn names_round_trip() {
+        let mut background = Background::Solid;
+        for _ in 0..NAMES.len() {
+            assert_eq!(Background::from_name(background.name()), Some(background));
+            background = background.next();
+        }
+
+        assert_eq!(background, Background::Solid);
+        assert_eq!(Background::from_name("Graph"), None);
+    }
+}
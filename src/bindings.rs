@@ -0,0 +1,370 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use dirs;
+use glium::glutin::{ModifiersState, VirtualKeyCode as KeyCode};
+use toml;
+
+use input::Input;
+
+/// Something the user can ask koko to do from the keyboard
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    ZoomIn,
+    ZoomOut,
+    HueRed,
+    HueGreen,
+    HueBlue,
+    PanUp,
+    PanDown,
+    PanLeft,
+    PanRight,
+    ToggleAntialiasing,
+    ToggleHighlighter,
+    CycleBlendMode,
+    CycleBackground,
+    ToggleSnapping,
+    ToggleMinimap,
+    ZoomToFit,
+    ZoomToStroke,
+    RecolorStroke,
+    ResetView,
+    GoTo,
+    AddBookmark,
+    NextBookmark,
+    PrevBookmark,
+    RotateLeft,
+    RotateRight,
+    ResetRotation,
+    OpacityUp,
+    OpacityDown,
+    ToggleHelp,
+}
+
+static ACTIONS: &[(Action, &str)] = &[
+    (Action::Quit,     "quit"),
+    (Action::ZoomIn,   "zoom_in"),
+    (Action::ZoomOut,  "zoom_out"),
+    (Action::HueRed,   "hue_red"),
+    (Action::HueGreen, "hue_green"),
+    (Action::HueBlue,  "hue_blue"),
+    (Action::PanUp,    "pan_up"),
+    (Action::PanDown,  "pan_down"),
+    (Action::PanLeft,  "pan_left"),
+    (Action::PanRight, "pan_right"),
+    (Action::ToggleAntialiasing, "toggle_antialiasing"),
+    (Action::ToggleHighlighter,  "toggle_highlighter"),
+    (Action::CycleBlendMode,     "cycle_blend_mode"),
+    (Action::CycleBackground,    "cycle_background"),
+    (Action::ToggleSnapping,     "toggle_snapping"),
+    (Action::ToggleMinimap,      "toggle_minimap"),
+    (Action::ZoomToFit,          "zoom_to_fit"),
+    (Action::ZoomToStroke,       "zoom_to_stroke"),
+    (Action::RecolorStroke,      "recolor_stroke"),
+    (Action::ResetView,          "reset_view"),
+    (Action::GoTo,               "go_to"),
+    (Action::AddBookmark,        "add_bookmark"),
+    (Action::NextBookmark,       "next_bookmark"),
+    (Action::PrevBookmark,       "prev_bookmark"),
+    (Action::RotateLeft,         "rotate_left"),
+    (Action::RotateRight,        "rotate_right"),
+    (Action::ResetRotation,      "reset_rotation"),
+    (Action::OpacityUp,          "opacity_up"),
+    (Action::OpacityDown,        "opacity_down"),
+    (Action::ToggleHelp,         "toggle_help"),
+];
+
+impl Action {
+    /// Every action, in the order they're listed in the help
+    pub fn all() -> impl Iterator<Item=Action> {
+        ACTIONS.iter().map(|&(action, _)| action)
+    }
+
+    /// The name used to refer to this action in the bindings file
+    pub fn name(&self) -> &'static str {
+        ACTIONS.iter()
+            .find(|&&(action, _)| action == *self)
+            .map(|&(_, name)| name)
+            .expect("action is missing from the name table")
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        ACTIONS.iter()
+            .find(|&&(_, action_name)| action_name == name)
+            .map(|&(action, _)| action)
+    }
+}
+
+/// A key pressed while holding some set of modifiers, e.g: `Ctrl+Shift+Z`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub key:  KeyCode,
+    pub mods: ModifiersState,
+}
+
+impl KeyChord {
+    pub fn new(key: KeyCode) -> KeyChord {
+        KeyChord { key, mods: ModifiersState::default() }
+    }
+
+    /// Parses a chord written as modifiers & a key joined by `+`, the
+    /// key names match glutin's `VirtualKeyCode` (ignoring case.)
+    pub fn parse(text: &str) -> Result<KeyChord, BindingsError> {
+        let mut mods = ModifiersState::default();
+        let mut key  = None;
+
+        for part in text.split('+').map(|part| part.trim()) {
+            match &part.to_lowercase()[..] {
+                "shift"                      => mods.shift = true,
+                "ctrl" | "control"           => mods.ctrl  = true,
+                "alt"                        => mods.alt   = true,
+                "logo" | "super" | "cmd"     => mods.logo  = true,
+                _ if key.is_none() => {
+                    key = Some(key_from_name(part)
+                        .ok_or_else(|| BindingsError::UnknownKey(part.to_string()))?);
+                },
+
+                _ => return Err(BindingsError::Malformed(format!("chord has more than one key: {}", text))),
+            }
+        }
+
+        match key {
+            Some(key) => Ok(KeyChord { key, mods }),
+            None => Err(BindingsError::Malformed(format!("chord has no key: {}", text))),
+        }
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.mods.ctrl  { write!(f, "Ctrl+")?;  }
+        if self.mods.shift { write!(f, "Shift+")?; }
+        if self.mods.alt   { write!(f, "Alt+")?;   }
+        if self.mods.logo  { write!(f, "Logo+")?;  }
+        write!(f, "{:?}", self.key)
+    }
+}
+
+#[derive(Debug)]
+pub enum BindingsError {
+    Io(io::Error),
+    Malformed(String),
+    UnknownAction(String),
+    UnknownKey(String),
+    Conflict(KeyChord, Action, Action),
+}
+
+impl fmt::Display for BindingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BindingsError::Io(ref err)            => write!(f, "i/o error reading bindings: {}", err),
+            BindingsError::Malformed(ref msg)     => write!(f, "malformed bindings: {}", msg),
+            BindingsError::UnknownAction(ref name) => write!(f, "unknown action: {}", name),
+            BindingsError::UnknownKey(ref name)   => write!(f, "unknown key: {}", name),
+            BindingsError::Conflict(chord, lhs, rhs) => {
+                write!(f, "{} is bound to both {} and {}", chord, lhs.name(), rhs.name())
+            },
+        }
+    }
+}
+
+impl Error for BindingsError {}
+
+impl From<io::Error> for BindingsError {
+    fn from(err: io::Error) -> BindingsError { BindingsError::Io(err) }
+}
+
+/// Maps each action to the key chords which trigger it.
+pub struct Bindings {
+    chords: HashMap<Action, Vec<KeyChord>>,
+}
+
+impl Bindings {
+    /// koko's stock keyboard layout
+    pub fn defaults() -> Bindings {
+        let mut chords = HashMap::new();
+        chords.insert(Action::Quit,     vec![KeyChord::new(KeyCode::Escape)]);
+        chords.insert(Action::ZoomIn,   vec![KeyChord::new(KeyCode::Equals)]);
+        chords.insert(Action::ZoomOut,  vec![KeyChord::new(KeyCode::Minus)]);
+        chords.insert(Action::HueRed,   vec![KeyChord::new(KeyCode::I)]);
+        chords.insert(Action::HueGreen, vec![KeyChord::new(KeyCode::O)]);
+        chords.insert(Action::HueBlue,  vec![KeyChord::new(KeyCode::P)]);
+        chords.insert(Action::PanUp,    vec![KeyChord::new(KeyCode::Up)]);
+        chords.insert(Action::PanDown,  vec![KeyChord::new(KeyCode::Down)]);
+        chords.insert(Action::PanLeft,  vec![KeyChord::new(KeyCode::Left)]);
+        chords.insert(Action::PanRight, vec![KeyChord::new(KeyCode::Right)]);
+        chords.insert(Action::ToggleAntialiasing, vec![KeyChord::new(KeyCode::A)]);
+        chords.insert(Action::ToggleHighlighter,  vec![KeyChord::new(KeyCode::H)]);
+        chords.insert(Action::CycleBlendMode,     vec![KeyChord::new(KeyCode::M)]);
+        chords.insert(Action::CycleBackground,    vec![KeyChord::new(KeyCode::G)]);
+        chords.insert(Action::ToggleSnapping,     vec![KeyChord::new(KeyCode::S)]);
+        chords.insert(Action::ToggleMinimap,      vec![KeyChord::new(KeyCode::Tab)]);
+        chords.insert(Action::ZoomToFit,          vec![KeyChord::new(KeyCode::F)]);
+        chords.insert(Action::ZoomToStroke,       vec![KeyChord { key: KeyCode::F, mods: ModifiersState { shift: true, ..Default::default() } }]);
+        chords.insert(Action::RecolorStroke,      vec![KeyChord::new(KeyCode::C)]);
+        chords.insert(Action::ResetView,          vec![KeyChord::new(KeyCode::Home)]);
+        chords.insert(Action::GoTo,               vec![KeyChord { key: KeyCode::G, mods: ModifiersState { ctrl: true, ..Default::default() } }]);
+        chords.insert(Action::AddBookmark,        vec![KeyChord::new(KeyCode::B)]);
+        chords.insert(Action::NextBookmark,       vec![KeyChord::new(KeyCode::N)]);
+        chords.insert(Action::PrevBookmark,       vec![KeyChord { key: KeyCode::N, mods: ModifiersState { shift: true, ..Default::default() } }]);
+        chords.insert(Action::RotateLeft,         vec![KeyChord::new(KeyCode::Comma)]);
+        chords.insert(Action::RotateRight,        vec![KeyChord::new(KeyCode::Period)]);
+        chords.insert(Action::ResetRotation,      vec![KeyChord::new(KeyCode::R)]);
+        chords.insert(Action::OpacityUp,          vec![KeyChord::new(KeyCode::RBracket)]);
+        chords.insert(Action::OpacityDown,        vec![KeyChord::new(KeyCode::LBracket)]);
+        chords.insert(Action::ToggleHelp,         vec![KeyChord::new(KeyCode::F1)]);
+
+        Bindings { chords }
+    }
+
+    /// Where the user's bindings live, e.g: `~/.config/koko/bindings.toml`
+    pub fn user_config_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("koko").join("bindings.toml"))
+    }
+
+    /// Loads the user's bindings if they have any, falling back to the
+    /// defaults (w/ a complaint) if they can't be loaded.
+    pub fn load_user_config() -> Bindings {
+        let path = match Bindings::user_config_path() {
+            Some(path) => path,
+            None => return Bindings::defaults(),
+        };
+
+        if !path.exists() { return Bindings::defaults() }
+
+        Bindings::load(&path).unwrap_or_else(|err| {
+            println!("could not load bindings @ {:?}: {}", path, err);
+            println!("falling back to default bindings");
+            Bindings::defaults()
+        })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Bindings, BindingsError> {
+        let mut source = String::new();
+        File::open(path)?.read_to_string(&mut source)?;
+        Bindings::from_toml(&source)
+    }
+
+    /// Parses a bindings file, which looks like:
+    ///
+    /// ```toml
+    /// [bindings]
+    /// quit    = "Ctrl+Q"
+    /// zoom_in = ["Equals", "Add"]
+    /// ```
+    ///
+    /// Actions which aren't listed keep their default bindings, an action
+    /// can be unbound by giving it an empty list.
+    pub fn from_toml(source: &str) -> Result<Bindings, BindingsError> {
+        let root = source.parse::<toml::Value>()
+            .map_err(|err| BindingsError::Malformed(err.to_string()))?;
+
+        let table = match root.get("bindings") {
+            Some(toml::Value::Table(table)) => table,
+            Some(_) => return Err(BindingsError::Malformed("`bindings` must be a table".to_string())),
+            None => return Ok(Bindings::defaults()),
+        };
+
+        let mut bindings = Bindings::defaults();
+        for (name, value) in table {
+            let action = Action::from_name(name)
+                .ok_or_else(|| BindingsError::UnknownAction(name.clone()))?;
+
+            let chords = match *value {
+                toml::Value::String(ref chord) => vec![KeyChord::parse(chord)?],
+                toml::Value::Array(ref chords) => {
+                    chords.iter().map(|chord| match chord.as_str() {
+                        Some(chord) => KeyChord::parse(chord),
+                        None => Err(BindingsError::Malformed(format!("{} must be a list of strings", name))),
+                    }).collect::<Result<Vec<_>, _>>()?
+                },
+
+                _ => return Err(BindingsError::Malformed(format!("{} must be a string or list", name))),
+            };
+
+            bindings.chords.insert(action, chords);
+        }
+
+        bindings.validate()?;
+        Ok(bindings)
+    }
+
+    /// Ensures no chord triggers more than one action.
+    pub fn validate(&self) -> Result<(), BindingsError> {
+        let mut seen: HashMap<KeyChord, Action> = HashMap::new();
+
+        // NOTE: walk the actions in a fixed order so errors are reproducible
+        for &(action, _) in ACTIONS {
+            for &chord in self.chords(action) {
+                match seen.get(&chord) {
+                    Some(&other) if other != action => return Err(BindingsError::Conflict(chord, other, action)),
+                    _ => { seen.insert(chord, action); },
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn chords(&self, action: Action) -> &[KeyChord] {
+        self.chords.get(&action).map_or(&[], |chords| &chords[..])
+    }
+
+    /// Responds true if any of the action's chords were pressed this frame.
+    pub fn was_pressed(&self, action: Action, input: &Input) -> bool {
+        self.chords(action).iter()
+            .any(|chord| self.is_best_match(chord, input.modifiers()) && input.was_key_pressed(chord.key))
+    }
+
+    /// Responds true if any of the action's chords are currently held down.
+    pub fn is_held(&self, action: Action, input: &Input) -> bool {
+        self.chords(action).iter()
+            .any(|chord| self.is_best_match(chord, input.modifiers()) && input.is_key_held(chord.key))
+    }
+
+    /// Extra modifiers are ignored (i.e: so holding `Shift` to snap doesn't stop
+    /// the view from panning) unless some other chord on the same key asks
+    /// for them, e.g: `Shift+N` doesn't also count as `N`.
+    fn is_best_match(&self, chord: &KeyChord, held: ModifiersState) -> bool {
+        mods_within(chord.mods, held) && !self.chords.values().flatten().any(|other| {
+            other.key == chord.key && other.mods != chord.mods
+                && mods_within(chord.mods, other.mods) && mods_within(other.mods, held)
+        })
+    }
+}
+
+/// Responds true if every modifier in `mods` is also in `held`
+fn mods_within(mods: ModifiersState, held: ModifiersState) -> bool {
+    (!mods.shift || held.shift) && (!mods.ctrl || held.ctrl) && (!mods.alt || held.alt) && (!mods.logo || held.logo)
+}
+
+macro_rules! key_names {
+    ($($key:ident),* $(,)*) => {
+        /// Looks up a `VirtualKeyCode` by its variant name, ignoring case.
+        pub fn key_from_name(name: &str) -> Option<KeyCode> {
+            $( if name.eq_ignore_ascii_case(stringify!($key)) { return Some(KeyCode::$key) } )*
+            None
+        }
+    }
+}
+
+key_names! {
+    Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9, Key0,
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Escape, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12, F13, F14, F15,
+    Snapshot, Scroll, Pause, Insert, Home, Delete, End, PageDown, PageUp,
+    Left, Up, Right, Down, Back, Return, Space, Compose, Caret, Numlock,
+    Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+    AbntC1, AbntC2, Add, Apostrophe, Apps, At, Ax, Backslash, Calculator, Capital, Colon, Comma,
+    Convert, Decimal, Divide, Equals, Grave, Kana, Kanji, LAlt, LBracket, LControl, LShift, LWin,
+    Mail, MediaSelect, MediaStop, Minus, Multiply, Mute, MyComputer, NavigateForward, NavigateBackward,
+    NextTrack, NoConvert, NumpadComma, NumpadEnter, NumpadEquals, OEM102, Period, PlayPause, Power,
+    PrevTrack, RAlt, RBracket, RControl, RShift, RWin, Semicolon, Slash, Sleep, Stop, Subtract,
+    Sysrq, Tab, Underline, Unlabeled, VolumeDown, VolumeUp, Wake, WebBack, WebFavorites, WebForward,
+    WebHome, WebRefresh, WebSearch, WebStop, Yen, Copy, Paste, Cut,
+}
@@ -0,0 +1,91 @@
+//! Named spots on the canvas to come back to, kept from one run to the next
+//! (see `session`.)
+
+use camera::Camera;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bookmark {
+    pub name:   String,
+    pub camera: Camera,
+}
+
+/// Bookmarks the view as `name`, moving an existing bookmark by the same
+/// name rather than adding another. Responds w/ where it ended up in the list.
+pub fn place(bookmarks: &mut Vec<Bookmark>, name: &str, camera: Camera) -> usize {
+    let bookmark = Bookmark { name: name.to_string(), camera };
+
+    match bookmarks.iter().position(|known| known.name == name) {
+        Some(idx) => { bookmarks[idx] = bookmark; idx },
+        None      => { bookmarks.push(bookmark); bookmarks.len() - 1 },
+    }
+}
+
+/// The bookmark `step` places after `current` (or before, if it's negative)
+/// wrapping around the ends of the list. W/o a current bookmark it starts
+/// from the first (or last) one.
+pub fn cycle(len: usize, current: Option<usize>, step: isize) -> Option<usize> {
+    if len == 0 { return None }
+
+    let from = match current {
+        Some(idx) => idx as isize,
+        None if step > 0 => -1,
+        None => 0,
+    };
+
+    Some((from + step).rem_euclid(len as isize) as usize)
+}
+
+/// The lowest number (counting up from 1) which isn't the name of a
+/// bookmark yet, for bookmarks left unnamed.
+pub fn unused_number(bookmarks: &[Bookmark]) -> String {
+    (1..).map(|number: usize| number.to_string())
+        .find(|name| find(bookmarks, name).is_none())
+        .expect("ran out of numbers")
+}
+
+/// Finds a bookmark by name, ignoring case
+pub fn find(bookmarks: &[Bookmark], name: &str) -> Option<usize> {
+    bookmarks.iter().position(|bookmark| bookmark.name.eq_ignore_ascii_case(name.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placing_a_name_twice_moves_the_bookmark() {
+        let mut bookmarks = vec![];
+        let there = Camera { center: [10.0, 20.0], ..Camera::home() };
+
+        assert_eq!(place(&mut bookmarks, "kitchen", Camera::home()), 0);
+        assert_eq!(place(&mut bookmarks, "attic", Camera::home()), 1);
+        assert_eq!(place(&mut bookmarks, "kitchen", there), 0);
+
+        assert_eq!(bookmarks.len(), 2);
+        assert_eq!(bookmarks[0].camera, there);
+        assert_eq!(find(&bookmarks, " Attic"), Some(1));
+        assert_eq!(find(&bookmarks, "garage"), None);
+    }
+
+    #[test]
+    fn unnamed_bookmarks_take_the_lowest_free_number() {
+        let mut bookmarks = vec![];
+        assert_eq!(unused_number(&bookmarks), "1");
+
+        place(&mut bookmarks, "1", Camera::home());
+        place(&mut bookmarks, "3", Camera::home());
+        assert_eq!(unused_number(&bookmarks), "2");
+
+        place(&mut bookmarks, "2", Camera::home());
+        assert_eq!(unused_number(&bookmarks), "4");
+    }
+
+    #[test]
+    fn cycling_wraps_around() {
+        assert_eq!(cycle(3, None, 1), Some(0));
+        assert_eq!(cycle(3, None, -1), Some(2));
+        assert_eq!(cycle(3, Some(2), 1), Some(0));
+        assert_eq!(cycle(3, Some(0), -1), Some(2));
+        assert_eq!(cycle(0, None, 1), None);
+    }
+}
@@ -0,0 +1,379 @@
+//! Moving the view around smoothly, see `CameraController`.
+//!
+//! Holding a key to pan, zoom or spin picks up speed (& slows down after)
+//! over a moment, at the same rate no matter the frame rate. Commands which
+//! take the view somewhere (framing the drawing, heading back to the origin,
+//! bookmarks, etc.) glide there instead (see `Tween`) so it's easy to tell
+//! where you ended up.
+
+use std::f32::consts::PI;
+
+use canvas::Stroke;
+use render::DAB_PX;
+
+/// How long it takes the view to get where it's going, in seconds
+pub static TWEEN_SECS: f64 = 0.3;
+
+/// Room left around the drawing when it's framed, as a fraction of the window
+pub static FIT_MARGIN: f32 = 0.05;
+
+/// Framing a tiny drawing doesn't zoom in any further than this
+pub static FIT_MAX_SCALE: f32 = 4.0;
+
+/// How close (in pixels on the screen) the cursor has to be to a stroke to pick it
+pub static PICK_PX: f32 = 4.0;
+
+/// How far the view can zoom out & in
+pub static MIN_SCALE: f32 = 0.05;
+pub static MAX_SCALE: f32 = 32.0;
+
+/// Top speeds w/ a key held: panning in pixels on the screen, zooming in
+/// doublings & spinning in degrees, all per second.
+pub static PAN_PX_PER_SEC:   f32 = 400.0;
+pub static ZOOM_PER_SEC:     f32 = 1.0;
+pub static SPIN_DEG_PER_SEC: f32 = 90.0;
+
+/// About how long it takes to get up to speed (or stop), in seconds
+pub static EASE_SECS: f64 = 0.08;
+
+/// Frames longer than this (e.g: after the window was dragged) are treated
+/// as if they were this long, so the view doesn't leap across the canvas.
+static MAX_STEP_SECS: f64 = 0.1;
+
+/// Speeds (relative to the top speed) below this are stopped
+static MIN_SPEED: f32 = 1e-3;
+
+/// Where the view is looking, see `Canvas::camera`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Camera {
+    /// the middle of the window, in world pixels (see `background`)
+    pub center: [f32; 2],
+
+    pub scale:    f32,
+    pub rotation: f32,
+}
+
+impl Camera {
+    /// Looking at the origin at 100%, the way koko starts up
+    pub fn home() -> Camera {
+        Camera { center: [0.0, 0.0], scale: 1.0, rotation: 0.0 }
+    }
+}
+
+/// `[min_x, min_y, max_x, max_y]` (in world pixels) of everything drawn by
+/// `strokes` at a level of detail (see `lod`), or nothing if they're empty.
+pub fn bounds(strokes: &[Stroke], lod: usize, dim: (u32, u32)) -> Option<[f32; 4]> {
+    strokes.iter()
+        .flat_map(|stroke| stroke.lod(lod).iter().map(move |point| (stroke.world_pos(point, dim), point.width())))
+        .fold(None, |acc, (pos, width)| {
+            let reach = DAB_PX * width / 2.0;
+            let [min_x, min_y, max_x, max_y] = acc.unwrap_or([f32::MAX, f32::MAX, f32::MIN, f32::MIN]);
+            Some([min_x.min(pos[0] - reach), min_y.min(pos[1] - reach), max_x.max(pos[0] + reach), max_y.max(pos[1] + reach)])
+        })
+}
+
+/// The topmost of `strokes` drawn over `pos` (in world pixels), or within
+/// `slop` world pixels of it.
+pub fn stroke_at(strokes: &[Stroke], pos: [f32; 2], slop: f32, dim: (u32, u32)) -> Option<usize> {
+    // NOTE: the line between each pair of samples is checked, rather than
+    //       just the samples, since fast strokes have gaps between them.
+    strokes.iter().rposition(|stroke| {
+        let points = stroke.lod(0);
+        (0..points.len()).any(|idx| {
+            let (from, to) = (&points[idx.saturating_sub(1)], &points[idx]);
+            let reach = DAB_PX * from.width().max(to.width()) / 2.0 + slop;
+            distance_to_line(pos, stroke.world_pos(from, dim), stroke.world_pos(to, dim)) <= reach
+        })
+    })
+}
+
+/// How far `pos` is from the closest point on the line from `from` to `to`
+fn distance_to_line(pos: [f32; 2], from: [f32; 2], to: [f32; 2]) -> f32 {
+    let (dx, dy) = (to[0] - from[0], to[1] - from[1]);
+    let length   = dx * dx + dy * dy;
+    let along    = if length > 0.0 { (((pos[0] - from[0]) * dx + (pos[1] - from[1]) * dy) / length).clamp(0.0, 1.0) } else { 0.0 };
+
+    let closest = [from[0] + dx * along, from[1] + dy * along];
+    ((pos[0] - closest[0]).powi(2) + (pos[1] - closest[1]).powi(2)).sqrt()
+}
+
+/// A view which fits `bounds` (in world pixels) in a window of `dim`,
+/// w/o changing how the view is spun.
+pub fn fit(bounds: [f32; 4], dim: (u32, u32), camera: Camera) -> Camera {
+    // NOTE: on a spun view the bounds take up more room on the screen, as
+    //       much as the box around them once they're spun.
+    let (width, height) = (bounds[2] - bounds[0], bounds[3] - bounds[1]);
+    let (sin, cos) = camera.rotation.sin_cos();
+    let spun = (width * cos.abs() + height * sin.abs(), width * sin.abs() + height * cos.abs());
+
+    let room  = (dim.0 as f32 * (1.0 - 2.0 * FIT_MARGIN), dim.1 as f32 * (1.0 - 2.0 * FIT_MARGIN));
+    let scale = (room.0 / spun.0.max(1.0)).min(room.1 / spun.1.max(1.0)).min(FIT_MAX_SCALE);
+
+    Camera {
+        center:   [(bounds[0] + bounds[2]) / 2.0, (bounds[1] + bounds[3]) / 2.0],
+        scale:    clamp_scale(scale),
+        rotation: camera.rotation,
+    }
+}
+
+/// Keeps the view from zooming out to nothing (or flipping inside out)
+pub fn clamp_scale(scale: f32) -> f32 {
+    scale.clamp(MIN_SCALE, MAX_SCALE)
+}
+
+/// The same angle, in `-PI ..= PI`
+pub fn wrap_angle(radians: f32) -> f32 {
+    (radians + PI).rem_euclid(2.0 * PI) - PI
+}
+
+/// Eases in & out, so the view doesn't lurch into motion or stop dead
+fn ease(t: f64) -> f32 {
+    let t = t.clamp(0.0, 1.0) as f32;
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// The view partway (`t` of the way) from `from` to `to`.
+///
+/// NOTE: zooming is done in steps of the same ratio, rather than the same
+///       size, otherwise it races through the zoomed in end. The view also
+///       spins the short way around.
+fn between(from: Camera, to: Camera, t: f32) -> Camera {
+    let scale = from.scale * (to.scale / from.scale).powf(t);
+    let turn = wrap_angle(to.rotation - from.rotation);
+    Camera {
+        center:   [from.center[0] + (to.center[0] - from.center[0]) * t,
+                   from.center[1] + (to.center[1] - from.center[1]) * t],
+        scale,
+        rotation: from.rotation + turn * t,
+    }
+}
+
+/// The view gliding from one place to another, over `TWEEN_SECS`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Tween {
+    pub from: Camera,
+    pub to:   Camera,
+
+    /// set by the first frame it's stepped on
+    started_at: Option<f64>,
+}
+
+impl Tween {
+    pub fn new(from: Camera, to: Camera) -> Tween {
+        Tween { from, to, started_at: None }
+    }
+
+    /// Where the view is `at` seconds in, & whether it's gotten there
+    pub fn step(&mut self, at: f64) -> (Camera, bool) {
+        let started_at = *self.started_at.get_or_insert(at);
+        let t = (at - started_at) / TWEEN_SECS;
+
+        match t >= 1.0 {
+            true  => (self.to, true),
+            false => (between(self.from, self.to, ease(t)), false),
+        }
+    }
+}
+
+/// Which way the keys held down are steering the view, each of these is
+/// `-1.0 ..= 1.0` of the top speed.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Steering {
+    /// right & up, on the screen
+    pub pan: [f32; 2],
+
+    /// in (or out, when it's negative)
+    pub zoom: f32,
+
+    /// clockwise on the screen
+    pub spin: f32,
+}
+
+impl Steering {
+    pub fn is_idle(&self) -> bool { *self == Steering::default() }
+}
+
+/// Moves the view for every navigation command: held keys speed it up &
+/// slow it down smoothly, and commands glide it somewhere w/ a `Tween`.
+/// Grabbing the view some other way (touch, dragging, etc.) should `stop` it.
+#[derive(Clone, Debug, Default)]
+pub struct CameraController {
+    tween: Option<Tween>,
+
+    /// how fast the view is moving, like `Steering` (but not at top speed yet)
+    velocity: Steering,
+
+    /// when the last frame started, if one has
+    last_at: Option<f64>,
+}
+
+impl CameraController {
+    pub fn new() -> CameraController {
+        Default::default()
+    }
+
+    /// Glides the view from `from` to `to`, unless it's steered or stopped on the way
+    pub fn move_to(&mut self, from: Camera, to: Camera) {
+        let to = Camera { scale: clamp_scale(to.scale), ..to };
+        self.tween = Some(Tween::new(from, to));
+    }
+
+    /// Stops the view right where it is
+    pub fn stop(&mut self) {
+        self.tween    = None;
+        self.velocity = Steering::default();
+    }
+
+    pub fn is_moving(&self) -> bool {
+        self.tween.is_some() || !self.velocity.is_idle()
+    }
+
+    /// Moves the view `camera` along to where it should be `at` seconds in,
+    /// or responds w/ nothing if it isn't moving. Steering takes over from
+    /// any glide that's underway.
+    pub fn step(&mut self, camera: Camera, steering: Steering, at: f64) -> Option<Camera> {
+        let dt = self.last_at.map_or(0.0, |last_at| (at - last_at).clamp(0.0, MAX_STEP_SECS));
+        self.last_at = Some(at);
+
+        if !steering.is_idle() { self.tween = None; }
+        if !self.is_moving() && steering.is_idle() { return None }
+
+        // NOTE: speeds close in on the steering exponentially, which is the
+        //       same no matter how the time is chopped up into frames.
+        let blend = (1.0 - (-dt / EASE_SECS).exp()) as f32;
+        let ease  = |speed: f32, toward: f32| {
+            let speed = speed + (toward - speed) * blend;
+            if speed.abs() < MIN_SPEED { 0.0 } else { speed }
+        };
+
+        self.velocity = Steering {
+            pan:  [ease(self.velocity.pan[0], steering.pan[0]), ease(self.velocity.pan[1], steering.pan[1])],
+            zoom: ease(self.velocity.zoom, steering.zoom),
+            spin: ease(self.velocity.spin, steering.spin),
+        };
+
+        let mut camera = match self.tween {
+            Some(ref mut tween) => {
+                let (camera, done) = tween.step(at);
+                if done { self.tween = None; }
+                camera
+            },
+
+            None => camera,
+        };
+
+        // panning goes along the screen, so it's spun into the world &
+        // slowed down (in the world) the further the view's zoomed in.
+        let dt = dt as f32;
+        let (sin, cos) = camera.rotation.sin_cos();
+        let [right, up] = [self.velocity.pan[0] * PAN_PX_PER_SEC * dt / camera.scale,
+                           self.velocity.pan[1] * PAN_PX_PER_SEC * dt / camera.scale];
+
+        camera.center[0] += cos * right - sin * up;
+        camera.center[1] += sin * right + cos * up;
+        camera.scale     = clamp_scale(camera.scale * (self.velocity.zoom * ZOOM_PER_SEC * dt).exp2());
+        camera.rotation  = wrap_angle(camera.rotation + (self.velocity.spin * SPIN_DEG_PER_SEC * dt).to_radians());
+
+        Some(camera)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tweens_ease_from_one_view_to_another() {
+        let from = Camera::home();
+        let to   = Camera { center: [100.0, -50.0], scale: 4.0, rotation: 0.0 };
+
+        let mut tween = Tween::new(from, to);
+        assert_eq!(tween.step(10.0), (from, false));
+
+        // halfway through it's zoomed in halfway, by ratio
+        let (middle, done) = tween.step(10.0 + TWEEN_SECS / 2.0);
+        assert!(!done);
+        assert_eq!(middle.center, [50.0, -25.0]);
+        assert!((middle.scale - 2.0).abs() < 1e-5, "scale was {}", middle.scale);
+
+        assert_eq!(tween.step(10.0 + TWEEN_SECS), (to, true));
+    }
+
+    #[test]
+    fn tweens_spin_the_short_way_around() {
+        let from = Camera { rotation: 0.1, ..Camera::home() };
+        let to   = Camera { rotation: 2.0 * PI - 0.1, ..Camera::home() };
+
+        let middle = between(from, to, 0.5);
+        assert!(middle.rotation.abs() < 1e-5, "rotation was {}", middle.rotation);
+    }
+
+    /// Steers a controller for `secs` at `fps`, then lets it coast to a stop
+    fn steer(steering: Steering, secs: f64, fps: f64) -> Camera {
+        let mut controller = CameraController::new();
+        let mut camera = Camera::home();
+
+        // NOTE: the first frame only starts the clock
+        let frames = (secs * fps).round() as usize;
+        assert_eq!(controller.step(camera, Steering::default(), 0.0), None);
+
+        for frame in 1..=frames * 2 {
+            let steering = if frame <= frames { steering } else { Steering::default() };
+            if let Some(moved) = controller.step(camera, steering, frame as f64 / fps) { camera = moved; }
+        }
+
+        assert!(!controller.is_moving());
+        camera
+    }
+
+    #[test]
+    fn steering_goes_as_far_at_any_frame_rate() {
+        let steering = Steering { pan: [1.0, 0.0], zoom: 0.0, spin: 0.0 };
+        let slow = steer(steering, 1.0, 30.0);
+        let fast = steer(steering, 1.0, 240.0);
+
+        // a second at top speed, give or take speeding up & slowing down
+        assert!((slow.center[0] - PAN_PX_PER_SEC).abs() < 2.0, "slow went {:?}", slow.center);
+        assert!((fast.center[0] - slow.center[0]).abs() < 2.0, "fast went {:?}, slow went {:?}", fast.center, slow.center);
+    }
+
+    #[test]
+    fn zooming_is_clamped() {
+        let zoom_out = Steering { zoom: -1.0, ..Steering::default() };
+        assert_eq!(steer(zoom_out, 30.0, 60.0).scale, MIN_SCALE);
+
+        let zoom_in = Steering { zoom: 1.0, ..Steering::default() };
+        let zoomed  = steer(zoom_in, 1.0, 60.0).scale;
+        assert!((zoomed - 2.0).abs() < 0.05, "zoomed to {}", zoomed);
+    }
+
+    #[test]
+    fn steering_takes_over_from_a_glide() {
+        let mut controller = CameraController::new();
+        controller.move_to(Camera::home(), Camera { center: [500.0, 0.0], ..Camera::home() });
+        assert_eq!(controller.step(Camera::home(), Steering::default(), 0.0), Some(Camera::home()));
+
+        let up = Steering { pan: [0.0, 1.0], ..Steering::default() };
+        let steered = controller.step(Camera::home(), up, 0.05).unwrap();
+        assert_eq!(steered.center[0], 0.0);
+        assert!(steered.center[1] > 0.0);
+
+        controller.stop();
+        assert!(!controller.is_moving());
+        assert_eq!(controller.step(steered, Steering::default(), 0.1), None);
+    }
+
+    #[test]
+    fn fitting_frames_the_bounds() {
+        let camera = fit([100.0, 0.0, 300.0, 100.0], (400, 300), Camera::home());
+        assert_eq!(camera.center, [200.0, 50.0]);
+        assert!((camera.scale - 1.8).abs() < 1e-5, "scale was {}", camera.scale);
+
+        // spun a quarter turn, it's the height of the window that's short
+        let spun = fit([100.0, 0.0, 300.0, 100.0], (400, 300), Camera { rotation: PI / 2.0, ..Camera::home() });
+        assert!((spun.scale - 1.35).abs() < 1e-4, "scale was {}", spun.scale);
+
+        // a dot isn't blown up to fill the window
+        assert_eq!(fit([0.0, 0.0, 1.0, 1.0], (400, 300), Camera::home()).scale, FIT_MAX_SCALE);
+    }
+}
@@ -0,0 +1,1602 @@
+use std::mem;
+
+use glium::glutin::{ElementState, MouseButton, VirtualKeyCode as KeyCode};
+
+use background::Background;
+use bookmarks::{self, Bookmark};
+use camera::{self, Camera, CameraController, Steering};
+use bindings::{Action, Bindings};
+use gesture::{Gesture, GestureRecognizer};
+use input::Input;
+use lod;
+use minimap::Minimap;
+use pen::VelocityPressure;
+use record::{Frame, InputEvent};
+use render::{self, View};
+use snap;
+use units::{V2, V2f};
+
+static MAX_SAMPLES: usize = 256;
+
+/// Width & opacity of a stroke at the lightest pressure, relative to full pressure
+static PEN_MIN_WIDTH:   f32 = 0.25;
+static PEN_MIN_OPACITY: f32 = 0.35;
+
+/// How much opacity changes w/ each press of `opacity_up` or `opacity_down`
+static OPACITY_STEP: f32 = 0.1;
+static MIN_OPACITY:  f32 = 0.1;
+
+/// Right-dragging closer than this to the middle of the window doesn't spin the view
+static MIN_SPIN_PX: f64 = 16.0;
+
+#[allow(dead_code)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BrushMode {
+    Normal,
+    Squareish,
+    WowSoEdgy,
+    Eraser,
+
+    /// translucent ink which darkens what's under it, w/o covering it up
+    Highlighter,
+}
+
+/// How a stroke is blended onto the strokes below it, see `render::composite`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    Normal,
+
+    /// darkens what's below, like a highlighter
+    Multiply,
+
+    /// lightens what's below, w/o ever going past white
+    Screen,
+
+    /// adds up to white where strokes overlap, for a glow
+    Add,
+
+    /// wipes out the strokes below, back to the background
+    Erase,
+}
+
+static BLEND_NAMES: &[(BlendMode, &str)] = &[
+    (BlendMode::Normal,   "normal"),
+    (BlendMode::Multiply, "multiply"),
+    (BlendMode::Screen,   "screen"),
+    (BlendMode::Add,      "add"),
+    (BlendMode::Erase,    "erase"),
+];
+
+impl BlendMode {
+    pub fn name(&self) -> &'static str {
+        BLEND_NAMES.iter()
+            .find(|&&(blend, _)| blend == *self)
+            .map(|&(_, name)| name)
+            .expect("blend mode is missing from the name table")
+    }
+
+    pub fn from_name(name: &str) -> Option<BlendMode> {
+        BLEND_NAMES.iter()
+            .find(|&&(_, known)| known.eq_ignore_ascii_case(name))
+            .map(|&(blend, _)| blend)
+    }
+
+    /// The next mode, for cycling through them all from the keyboard
+    pub fn next(&self) -> BlendMode {
+        match *self {
+            BlendMode::Normal   => BlendMode::Multiply,
+            BlendMode::Multiply => BlendMode::Screen,
+            BlendMode::Screen   => BlendMode::Add,
+            BlendMode::Add      => BlendMode::Erase,
+            BlendMode::Erase    => BlendMode::Normal,
+        }
+    }
+}
+
+/// Represents a mouse-input sample from some brush
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ControlPoint {
+    pub screen_xy: V2,
+
+    /// `0.0 ..= 1.0`, from the pen if it reports any or simulated otherwise
+    pub pressure: f32,
+
+    /// `-1.0 ..= 1.0` along each axis, only known for some pens
+    pub tilt: Option<V2f>,
+}
+
+impl ControlPoint {
+    /// Size of the brush at this point, relative to its size at full pressure
+    pub fn width(&self) -> f32 {
+        let width = PEN_MIN_WIDTH + (1.0 - PEN_MIN_WIDTH) * self.pressure;
+
+        // tipping the pen over lays more of the nib down, like shading w/ a pencil
+        match self.tilt {
+            Some(tilt) => width * (1.0 + 0.5 * tilt.length().min(1.0) as f32),
+            None => width,
+        }
+    }
+
+    pub fn opacity(&self) -> f32 {
+        let opacity = PEN_MIN_OPACITY + (1.0 - PEN_MIN_OPACITY) * self.pressure;
+
+        match self.tilt {
+            Some(tilt) => opacity * (1.0 - 0.3 * tilt.length().min(1.0) as f32),
+            None => opacity,
+        }
+    }
+}
+
+/// A finished stroke, w/ its samples corrected for the view it was drawn in
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stroke {
+    pub samples: Vec<ControlPoint>,
+    pub scale:   f32,
+
+    pub brush:   BrushMode,
+    pub blend:   BlendMode,
+
+    /// `0.0 ..= 1.0`, of the whole stroke: its dabs don't build up where they overlap
+    pub opacity: f32,
+
+    /// RGB of the ink, highlighters ignore it
+    pub color: (u8, u8, u8),
+
+    /// simplified copies of `samples` for when the view is zoomed out, see `lod`
+    pub lods: Vec<Vec<ControlPoint>>,
+}
+
+impl Stroke {
+    pub fn new(dim: (u32, u32), scale: f32, rotation: f32, scanbox: V2, points: Vec<ControlPoint>) -> Stroke {
+        // NOTE: correct the cursor's position in the unit square to it's relative position
+        //       by adding the current offset of the scanbox, and undo the view's rotation
+        //       (around the center of the window) so the path stays where it was drawn.
+        //
+        // TODO: would be nice if the extremes of this path were stored in some sort
+        //       of spatial data-structure so we can quickly query if the path is currently
+        //       inside the scanbox -- this would enable some optimizations like skipping
+        //       rendering and possibly removing out-of-bounds paths from VRAM.
+        //
+        let (center_x, center_y) = (dim.0 as f64 / 2.0, dim.1 as f64 / 2.0);
+        let corrected_samples = points.iter().map(|point| {
+            let from_center = V2f(point.screen_xy.0 as f64 - center_x, point.screen_xy.1 as f64 - center_y);
+            let V2f(rot_x, rot_y) = rotate_screen(from_center, -rotation);
+
+            let adj_x = (rot_x + center_x) as f32 + (scanbox.0 as f32 / 2.0);
+            let adj_y = (rot_y + center_y) as f32 - (scanbox.1 as f32 / 2.0);
+
+            ControlPoint { screen_xy: V2(adj_x as i64, adj_y as i64), ..*point }
+        }).collect::<Vec<_>>();
+
+        // NOTE: dabs are a fixed size on the screen, so they're bigger (in the
+        //       stroke's own pixels) the further in the view was zoomed.
+        let lods = lod::build(&corrected_samples, render::DAB_PX * scale);
+
+        Stroke {
+            samples: corrected_samples,
+            scale,
+            brush:   BrushMode::Normal,
+            blend:   BlendMode::Normal,
+            opacity: 1.0,
+            color:   render::COLOR_PEN,
+            lods,
+        }
+    }
+
+    /// The color of the ink, as expected by shaders
+    pub fn ink(&self) -> [f32; 3] {
+        [self.color.0 as f32 / 255.0, self.color.1 as f32 / 255.0, self.color.2 as f32 / 255.0]
+    }
+
+    /// Where one of its samples is in world pixels (see `background`) w/
+    /// the window at `dim`, i.e: w/o the view's `View::apply`.
+    pub fn world_pos(&self, point: &ControlPoint, dim: (u32, u32)) -> [f32; 2] {
+        let (half_w, half_h) = (dim.0 as f32 / 2.0, dim.1 as f32 / 2.0);
+        [(point.screen_xy.0 as f32 - half_w) / self.scale, (half_h - point.screen_xy.1 as f32) / self.scale]
+    }
+
+    /// The samples to draw at a level of detail from `lod::level_for`
+    pub fn lod(&self, level: usize) -> &[ControlPoint] {
+        match level {
+            0 => &self.samples,
+            _ => &self.lods[(level - 1).min(self.lods.len() - 1)],
+        }
+    }
+}
+
+/// Everything koko knows about the drawing & the view of it, updated
+/// frame by frame from input events. Doesn't touch the GPU, so it can be
+/// driven w/o a window (see `record::replay`.)
+pub struct Canvas {
+    pub is_running: bool,
+    pub window_dim: (u32, u32),
+
+    pub brush:   BrushMode,
+    pub color:   (u8, u8, u8),
+    pub blend:   BlendMode,
+    pub opacity: f32,
+    pub scale:   f32,
+
+    /// where the view is, see `look_at`. NOTE: it's only rounded to whole
+    /// pixels by `view` (for drawing) so slow moves still add up.
+    pub scanbox: V2f,
+
+    /// clockwise (on screen) rotation of the view, in radians
+    pub rotation: f32,
+
+    /// drawn behind the strokes, it's part of the drawing like they are
+    pub background: Background,
+
+    /// smooth the edges of strokes in the shader (& overlays w/ multisampling)
+    pub antialiasing: bool,
+
+    /// snap the ends of strokes to the grid & the ends of other strokes
+    pub snapping: bool,
+
+    /// show a map of the whole drawing in the corner, see `minimap`
+    pub minimap: bool,
+
+    /// list every key binding in the middle of the screen
+    pub help: bool,
+
+    /// a line of text being typed in, which the HUD shows while it's open
+    pub prompt: Option<Prompt>,
+
+    /// spots on the canvas to come back to, see `bookmarks`
+    pub bookmarks: Vec<Bookmark>,
+
+    /// the bookmark last made or jumped to, cycling starts from it
+    pub bookmark: Option<usize>,
+
+    pub strokes: Vec<Stroke>,
+
+    /// samples of the stroke currently being drawn, in screen space
+    pub pending: Vec<ControlPoint>,
+
+    /// where each of the `pending` samples is in the world (in world pixels)
+    pending_world: Vec<[f32; 2]>,
+
+    controller:     Input,
+    bindings:       Bindings,
+    touch:          GestureRecognizer,
+    touch_gestures: Vec<Gesture>,
+    sim_pressure:   VelocityPressure,
+
+    /// the minimap (as it was laid out when it was clicked) while it's being dragged on
+    minimap_drag:   Option<Minimap>,
+
+    /// moves the view for the keyboard & commands, see `camera`
+    camera_controller: CameraController,
+}
+
+impl Canvas {
+    pub fn new(bindings: Bindings) -> Canvas {
+        Canvas {
+            is_running: true,
+            window_dim: (1280, 720), // TODO: init to actual window size
+
+            brush:   BrushMode::Squareish,
+            color:   render::COLOR_PEN,
+            blend:   BlendMode::Normal,
+            opacity: 1.0,
+            scale:   1.0,
+            scanbox: V2f(0.0, 0.0),
+
+            rotation: 0.0,
+
+            background:   Background::Solid,
+            antialiasing: true,
+            snapping:     false,
+            minimap:      false,
+            help:         false,
+            prompt:       None,
+            bookmarks:    vec![],
+            bookmark:     None,
+
+            strokes: vec![],
+            pending: Vec::with_capacity(MAX_SAMPLES),
+            pending_world: Vec::with_capacity(MAX_SAMPLES),
+
+            controller:     Input::new(),
+            bindings,
+            touch:          GestureRecognizer::new(),
+            touch_gestures: vec![],
+            sim_pressure:   VelocityPressure::new(),
+            minimap_drag:   None,
+            camera_controller: CameraController::new(),
+        }
+    }
+
+    /// Runs one frame w/ the events which arrived since the last one.
+    pub fn step(&mut self, frame: &Frame) {
+        self.controller.begin_new_frame();
+        for timed in &frame.events { self.handle_event(&timed.event); }
+        self.update(frame.at);
+    }
+
+    /// Position of the cursor in pixels from the upper left of the window
+    pub fn cursor(&self) -> V2f { self.controller.cursor() }
+
+    pub fn bindings(&self) -> &Bindings { &self.bindings }
+
+    /// The topmost stroke the cursor is over (or close to), see `camera::stroke_at`
+    pub fn stroke_under_cursor(&self) -> Option<usize> {
+        let (x, y) = render::screen_to_unit(self.cursor(), self.window_dim);
+        let pos    = self.view().unapply_world([x, y]);
+        camera::stroke_at(&self.strokes, pos, camera::PICK_PX / self.scale, self.window_dim)
+    }
+
+    pub fn view(&self) -> View {
+        View {
+            dim:      self.window_dim,
+            scale:    self.scale,
+            scanbox:  V2(self.scanbox.0.round() as i64, self.scanbox.1.round() as i64),
+            rotation: self.rotation,
+        }
+    }
+
+    /// Where the minimap is & what it shows, which stays put while it's being dragged on
+    pub fn minimap_layout(&self) -> Minimap {
+        self.minimap_drag.unwrap_or_else(|| Minimap::new(self))
+    }
+
+    pub fn camera(&self) -> Camera {
+        Camera {
+            center:   [(self.scanbox.0 / (2.0 * self.scale as f64)) as f32, (self.scanbox.1 / (2.0 * self.scale as f64)) as f32],
+            scale:    self.scale,
+            rotation: self.rotation,
+        }
+    }
+
+    pub fn set_camera(&mut self, camera: Camera) {
+        self.scale    = camera.scale;
+        self.rotation = camera.rotation;
+        self.look_at(camera.center);
+    }
+
+    /// Glides the view over to `camera`, unless it's interrupted
+    pub fn move_camera(&mut self, camera: Camera) {
+        let from = self.camera();
+        self.camera_controller.move_to(from, camera);
+    }
+
+    /// Pans the view so `pos` (in world pixels) is in the middle of the window
+    ///
+    /// NOTE: the middle of the view is at `scanbox / (2 * scale)` in the
+    ///       world, and spinning the view doesn't move it.
+    pub fn look_at(&mut self, pos: [f32; 2]) {
+        let scale = 2.0 * self.scale as f64;
+        self.scanbox = V2f(scale * pos[0] as f64, scale * pos[1] as f64);
+    }
+
+    /// Number of verts it takes to draw every finished stroke
+    pub fn verts(&self) -> usize {
+        self.strokes.iter().map(|stroke| stroke.samples.len() * 6).sum()
+    }
+
+    fn handle_event(&mut self, event: &InputEvent) {
+        match *event {
+            InputEvent::CloseRequested => self.is_running = false,
+            InputEvent::Resized(width, height) => self.window_dim = (width, height),
+
+            InputEvent::CursorMoved(pos, modifiers) => {
+                self.controller.cursor_moved_event(pos);
+                self.controller.modifiers_event(modifiers);
+            },
+
+            InputEvent::MouseInput(state, button, modifiers) => {
+                match state {
+                    ElementState::Pressed  => self.controller.button_down_event(button),
+                    ElementState::Released => self.controller.button_up_event(button),
+                };
+
+                self.controller.modifiers_event(modifiers);
+            },
+
+            InputEvent::MouseWheel(delta, modifiers) => {
+                self.controller.wheel_event(delta);
+                self.controller.modifiers_event(modifiers);
+            },
+
+            InputEvent::Touch(id, phase, pos) => {
+                let gestures = self.touch.touch_event(id, phase, pos);
+                self.touch_gestures.extend(gestures);
+            },
+
+            InputEvent::AxisMotion(device, axis, value) => self.controller.axis_event(device, axis, value),
+
+            // NOTE: keys typed into the prompt aren't seen by the bindings
+            InputEvent::Key(ElementState::Pressed, key, modifiers) if self.prompt.is_some() => self.type_prompt(key, modifiers.shift),
+
+            InputEvent::Key(state, key, modifiers) => {
+                self.controller.modifiers_event(modifiers);
+                match state {
+                    ElementState::Pressed  => self.controller.key_down_event(key),
+                    ElementState::Released => self.controller.key_up_event(key),
+                };
+            },
+        }
+    }
+
+    fn update(&mut self, at: f64) {
+        let before = self.view();
+
+        // handle user keyboard input
+        if self.bindings.was_pressed(Action::Quit, &self.controller) {
+            self.is_running = false;
+        }
+
+        if self.bindings.was_pressed(Action::ToggleAntialiasing, &self.controller) {
+            self.antialiasing = !self.antialiasing;
+        }
+
+        // NOTE: highlighters darken what's under them by default, but any
+        //       other mode can be picked for them afterwards.
+        if self.bindings.was_pressed(Action::ToggleHighlighter, &self.controller) {
+            let (brush, blend) = match self.brush {
+                BrushMode::Highlighter => (BrushMode::Squareish, BlendMode::Normal),
+                _ => (BrushMode::Highlighter, BlendMode::Multiply),
+            };
+
+            self.brush = brush;
+            self.blend = blend;
+        }
+
+        if self.bindings.was_pressed(Action::CycleBlendMode, &self.controller) {
+            self.blend = self.blend.next();
+        }
+
+        if self.bindings.was_pressed(Action::CycleBackground, &self.controller) {
+            self.background = self.background.next();
+        }
+
+        if self.bindings.was_pressed(Action::ToggleSnapping, &self.controller) {
+            self.snapping = !self.snapping;
+        }
+
+        if self.bindings.was_pressed(Action::ToggleMinimap, &self.controller) {
+            self.minimap = !self.minimap;
+        }
+
+        if self.bindings.was_pressed(Action::ToggleHelp, &self.controller) {
+            self.help = !self.help;
+        }
+
+        if self.bindings.was_pressed(Action::ZoomToFit, &self.controller) {
+            if let Some(bounds) = camera::bounds(&self.strokes, 0, self.window_dim) {
+                let framed = camera::fit(bounds, self.window_dim, self.camera());
+                self.move_camera(framed);
+            }
+        }
+
+        // frames just the stroke under the cursor, if there is one
+        if self.bindings.was_pressed(Action::ZoomToStroke, &self.controller) {
+            let picked = self.stroke_under_cursor();
+            if let Some(bounds) = picked.and_then(|idx| camera::bounds(&self.strokes[idx..=idx], 0, self.window_dim)) {
+                let framed = camera::fit(bounds, self.window_dim, self.camera());
+                self.move_camera(framed);
+            }
+        }
+
+        // paints the stroke under the cursor w/ the current hue
+        if self.bindings.was_pressed(Action::RecolorStroke, &self.controller) {
+            if let Some(idx) = self.stroke_under_cursor() {
+                self.strokes[idx].color = self.color;
+            }
+        }
+
+        if self.bindings.was_pressed(Action::ResetView, &self.controller) {
+            self.move_camera(Camera::home());
+        }
+
+        if self.bindings.was_pressed(Action::GoTo, &self.controller) {
+            self.prompt = Some(Prompt::new(PromptKind::GoTo));
+        }
+
+        if self.bindings.was_pressed(Action::AddBookmark, &self.controller) {
+            self.prompt = Some(Prompt::new(PromptKind::Bookmark));
+        }
+
+        let step = if self.bindings.was_pressed(Action::NextBookmark, &self.controller) { 1 }
+            else if self.bindings.was_pressed(Action::PrevBookmark, &self.controller) { -1 }
+            else { 0 };
+
+        if step != 0 {
+            if let Some(idx) = bookmarks::cycle(self.bookmarks.len(), self.bookmark, step) {
+                self.jump_to_bookmark(idx);
+            }
+        }
+
+        if self.bindings.was_pressed(Action::OpacityUp, &self.controller) {
+            self.opacity = (self.opacity + OPACITY_STEP).min(1.0);
+        } else if self.bindings.was_pressed(Action::OpacityDown, &self.controller) {
+            self.opacity = (self.opacity - OPACITY_STEP).max(MIN_OPACITY);
+        }
+
+        if self.bindings.is_held(Action::HueRed, &self.controller) {
+            self.color.0 = self.color.0.wrapping_add(0x01);
+        } else if self.bindings.is_held(Action::HueGreen, &self.controller) {
+            self.color.1 = self.color.1.wrapping_add(0x01);
+        } else if self.bindings.is_held(Action::HueBlue, &self.controller) {
+            self.color.2 = self.color.2.wrapping_add(0x01);
+        }
+
+        if self.bindings.was_pressed(Action::ResetRotation, &self.controller) {
+            let upright = Camera { rotation: 0.0, ..self.camera() };
+            self.move_camera(upright);
+        }
+
+        // held keys steer the view, which speeds up & slows down smoothly
+        let held  = |action| if self.bindings.is_held(action, &self.controller) { 1.0 } else { 0.0 };
+        let steering = Steering {
+            pan:  [held(Action::PanRight) - held(Action::PanLeft), held(Action::PanUp) - held(Action::PanDown)],
+            zoom: held(Action::ZoomIn) - held(Action::ZoomOut),
+            spin: held(Action::RotateRight) - held(Action::RotateLeft),
+        };
+
+        let camera = self.camera();
+        if let Some(camera) = self.camera_controller.step(camera, steering, at) {
+            self.set_camera(camera);
+        }
+
+        // handle touch input
+        // one finger draws just like the mouse, two fingers move the view.
+        //
+        // NOTE: fingers can't tell us how hard they're pressing (at least not
+        //       through winit) so their pressure is always simulated.
+        //
+        let touch_gestures = mem::take(&mut self.touch_gestures);
+        for gesture in touch_gestures {
+            match gesture {
+                Gesture::StrokeBegin(pos) | Gesture::StrokeMove(pos) => {
+                    if let Gesture::StrokeBegin(_) = gesture {
+                        self.sim_pressure.reset();
+                        self.camera_controller.stop();
+                        self.clear_pending();
+                    }
+
+                    let pressure = self.sim_pressure.sample(pos, at);
+                    self.push_sample(pos, pressure, None);
+                },
+
+                Gesture::StrokeEnd    => self.commit_stroke(),
+                Gesture::StrokeCancel => self.clear_pending(),
+
+                // NOTE: the scanbox moves at half speed & opposite the paths,
+                //       and is laid out before the view is rotated.
+                Gesture::Pan(delta) => {
+                    self.camera_controller.stop();
+                    let V2f(dx, dy) = rotate_screen(delta, -self.rotation);
+                    self.scanbox = self.scanbox + V2f(-2.0 * dx, 2.0 * dy);
+                },
+
+                // NOTE: the spot between the fingers stays put, the middle of
+                //       the view moves toward (or away from) it as it zooms.
+                Gesture::Zoom { factor, center } => {
+                    self.camera_controller.stop();
+
+                    let (x, y)  = render::screen_to_unit(center, self.window_dim);
+                    let pinched = self.view().unapply_world([x, y]);
+                    let camera  = self.camera();
+                    let scale   = camera::clamp_scale(camera.scale * factor as f32);
+                    let shrink  = camera.scale / scale;
+
+                    self.set_camera(Camera {
+                        center: [pinched[0] + (camera.center[0] - pinched[0]) * shrink,
+                                 pinched[1] + (camera.center[1] - pinched[1]) * shrink],
+                        scale,
+                        ..camera
+                    });
+                },
+
+                Gesture::Rotate { radians, .. } => {
+                    self.camera_controller.stop();
+                    self.rotation = camera::wrap_angle(self.rotation + radians as f32);
+                },
+            }
+        }
+
+        // dragging w/ the right button spins the view around the middle of
+        // the window, like turning a sheet of paper under the pen.
+        //
+        // NOTE: the cursor has to be a ways from the middle, since the angle
+        //       swings wildly right next to it.
+        //
+        if self.controller.is_button_held(MouseButton::Right) {
+            let middle = V2f(self.window_dim.0 as f64 / 2.0, self.window_dim.1 as f64 / 2.0);
+            let now    = self.controller.cursor() - middle;
+            let then   = now - self.controller.cursor_delta();
+
+            self.camera_controller.stop();
+            if now.length() > MIN_SPIN_PX && then.length() > MIN_SPIN_PX {
+                let turn = now.1.atan2(now.0) - then.1.atan2(then.0);
+                self.rotation = camera::wrap_angle(self.rotation + turn as f32);
+            }
+        }
+
+        // the stroke in progress stays put on the canvas when the view moves
+        // under it, rather than on the screen.
+        if self.view() != before { self.reproject_pending(); }
+
+        // handle cursor input
+        // store the user input into screen-relative control points
+        // and then offset them based on the current scanbox.
+        //
+        // pens tell us how hard they're pressing, for anything else
+        // we guess from how fast the cursor is moving.
+        //
+        // NOTE: clicking on the minimap moves the view instead of drawing, and
+        //       it keeps the layout it had when it was clicked until the
+        //       button is let go. (otherwise it'd shift around as the view
+        //       moves, under the cursor.)
+        //
+        let cursor = self.controller.cursor();
+        // NOTE: putting the pen down stops the view, like pinning down the paper
+        if self.controller.was_button_pressed(MouseButton::Left) {
+            self.sim_pressure.reset();
+            self.camera_controller.stop();
+
+            let minimap = self.minimap_layout();
+            if self.minimap && minimap.contains(cursor) { self.minimap_drag = Some(minimap); }
+        }
+
+        // NOTE: a click on the minimap glides over to it, dragging follows the cursor
+        if let Some(minimap) = self.minimap_drag {
+            let there = minimap.world_at(cursor);
+            if self.controller.was_button_pressed(MouseButton::Left) {
+                let camera = Camera { center: there, ..self.camera() };
+                self.move_camera(camera);
+            } else if self.controller.cursor_delta() != V2f(0.0, 0.0) {
+                self.camera_controller.stop();
+                self.look_at(there);
+            }
+
+            if !self.controller.is_button_held(MouseButton::Left) { self.minimap_drag = None; }
+        } else if self.controller.is_button_held(MouseButton::Left) {
+            let simulated = self.sim_pressure.sample(cursor, at);
+            let pressure  = self.controller.pen_pressure().unwrap_or(simulated);
+            let tilt      = self.controller.pen_tilt();
+            self.push_sample(cursor, pressure, tilt);
+        } else if self.controller.was_button_released(MouseButton::Left) {
+            self.commit_stroke();
+        }
+    }
+
+    /// Types a key into the prompt, which is sent off w/ enter or closed w/ escape
+    fn type_prompt(&mut self, key: KeyCode, shift: bool) {
+        let typed = match key {
+            KeyCode::Back => {
+                if let Some(ref mut prompt) = self.prompt { prompt.text.pop(); }
+                return;
+            },
+
+            KeyCode::Return | KeyCode::NumpadEnter => {
+                if let Some(prompt) = self.prompt.take() { self.submit_prompt(prompt); }
+                return;
+            },
+
+            KeyCode::Escape => { self.prompt = None; return },
+
+            key => match typed_char(key, shift) {
+                Some(typed) => typed,
+                None => return,
+            },
+        };
+
+        if let Some(ref mut prompt) = self.prompt { prompt.text.push(typed); }
+    }
+
+    fn submit_prompt(&mut self, prompt: Prompt) {
+        match prompt.kind {
+            // NOTE: this takes the name of a bookmark or a spot in the world
+            //       (in world pixels, w/ y pointing up) as `x, y`. bookmarks
+            //       come first, so one named like a spot can still be reached.
+            //       anything else closes the prompt w/o going anywhere.
+            PromptKind::GoTo => {
+                let pos = prompt.text.split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|part| !part.is_empty())
+                    .map(|part| part.parse::<f32>())
+                    .collect::<Result<Vec<_>, _>>();
+
+                match (bookmarks::find(&self.bookmarks, &prompt.text), pos) {
+                    (Some(idx), _) => self.jump_to_bookmark(idx),
+                    (None, Ok(ref pos)) if pos.len() == 2 => {
+                        let there = Camera { center: [pos[0], pos[1]], ..self.camera() };
+                        self.move_camera(there);
+                    },
+
+                    _ => {},
+                }
+            },
+
+            // NOTE: bookmarks left unnamed are numbered instead, w/ a number
+            //       no other bookmark has (so one isn't moved by accident)
+            PromptKind::Bookmark => {
+                let name = match prompt.text.trim() {
+                    "" => bookmarks::unused_number(&self.bookmarks),
+                    name => name.to_string(),
+                };
+
+                let camera = self.camera();
+                self.bookmark = Some(bookmarks::place(&mut self.bookmarks, &name, camera));
+            },
+        }
+    }
+
+    fn jump_to_bookmark(&mut self, idx: usize) {
+        let camera = self.bookmarks[idx].camera;
+        self.bookmark = Some(idx);
+        self.move_camera(camera);
+    }
+
+    /// Moves the samples of the stroke in progress to where they are on the
+    /// screen in the current view.
+    ///
+    /// NOTE: they're placed from where they are in the world, rather than
+    ///       where they were on the screen, otherwise rounding them to whole
+    ///       pixels every frame adds up.
+    fn reproject_pending(&mut self) {
+        let (dim, view) = (self.window_dim, self.view());
+        for (point, &world) in self.pending.iter_mut().zip(&self.pending_world) {
+            let V2f(x, y) = render::unit_to_screen(view.apply_world(world), dim);
+            point.screen_xy = V2(x.round() as i64, y.round() as i64);
+        }
+    }
+
+    fn clear_pending(&mut self) {
+        self.pending.clear();
+        self.pending_world.clear();
+    }
+
+    fn push_sample(&mut self, pos: V2f, pressure: f32, tilt: Option<V2f>) {
+        let pos = self.snap(pos, false);
+        let screen_xy = V2(pos.0 as i64, pos.1 as i64);
+        let (x, y) = render::screen_to_unit(V2f(screen_xy.0 as f64, screen_xy.1 as f64), self.window_dim);
+
+        self.pending_world.push(self.view().unapply_world([x, y]));
+        self.pending.push(ControlPoint {
+            screen_xy,
+            pressure,
+            tilt,
+        });
+
+        if self.controller.modifiers().shift { self.straighten_pending(); }
+    }
+
+    /// Lays every sample of the stroke in progress onto one line out from
+    /// its start, at the multiple of `snap::ANGLE_STEP_DEG` nearest to the
+    /// newest sample.
+    ///
+    /// NOTE: the whole stroke is laid out again for each sample, snapping
+    ///       each one on its own zigzags between angles as the pen drifts.
+    fn straighten_pending(&mut self) {
+        let (start, newest) = match (self.pending_world.first(), self.pending_world.last()) {
+            (Some(&start), Some(&newest)) => (start, newest),
+            _ => return,
+        };
+
+        let angle = match snap::ray_angle(start, newest) {
+            Some(angle) => angle,
+            None => return,
+        };
+
+        for world in &mut self.pending_world[1..] { *world = snap::onto_ray(start, angle, *world); }
+        self.reproject_pending();
+    }
+
+    /// Snaps a sample of the stroke in progress (in pixels on the screen)
+    /// in world space, see `snap`.
+    ///
+    /// NOTE: only the ends of a stroke snap to things (so freehand lines stay
+    ///       freehand) and nothing does w/ shift held, the stroke's kept to
+    ///       a straight line instead (see `straighten_pending`.)
+    fn snap(&self, pos: V2f, is_end: bool) -> V2f {
+        let view  = self.view();
+        let world = |pos: V2f| {
+            let (x, y) = render::screen_to_unit(pos, self.window_dim);
+            view.unapply_world([x, y])
+        };
+
+        let start = self.pending.first().map(|point| world(V2f(point.screen_xy.0 as f64, point.screen_xy.1 as f64)));
+        let at    = world(pos);
+
+        let snapped = match start {
+            Some(_) if self.controller.modifiers().shift => None,
+
+            _ if self.snapping && (start.is_none() || is_end) => {
+                let dim  = self.window_dim;
+                let ends = self.strokes.iter().flat_map(|stroke| {
+                    stroke.samples.first().into_iter().chain(stroke.samples.last())
+                        .map(move |point| stroke.world_pos(point, dim))
+                });
+
+                // NOTE: a stroke can also be closed up by ending it where it started
+                let targets = snap::grid_point(self.background, at).into_iter().chain(ends).chain(start);
+                snap::nearest(targets, at, snap::SNAP_PX / self.scale.abs())
+            },
+
+            _ => None,
+        };
+
+        // NOTE: samples are kept in whole pixels, so snapped ones are rounded
+        //       to the nearest rather than cut off.
+        match snapped {
+            Some(snapped) => {
+                let V2f(x, y) = render::unit_to_screen(view.apply_world(snapped), self.window_dim);
+                V2f(x.round(), y.round())
+            },
+            None => pos,
+        }
+    }
+
+    /// Turns the stroke in progress into a finished one, if there is one
+    fn commit_stroke(&mut self) {
+        if self.pending.is_empty() { return }
+
+        // the end of the stroke snaps to things, just like its start
+        let end = self.pending.len() - 1;
+        let end_xy = self.pending[end].screen_xy;
+        let V2f(x, y) = self.snap(V2f(end_xy.0 as f64, end_xy.1 as f64), true);
+        self.pending[end].screen_xy = V2(x as i64, y as i64);
+
+        // swap the input buffer with a fresh one
+        let mut input_buf = Vec::with_capacity(MAX_SAMPLES);
+        mem::swap(&mut self.pending, &mut input_buf);
+        self.pending_world.clear();
+
+        // NOTE: laid down where it was drawn on the screen, w/ the scanbox rounded the same way
+        let mut stroke = Stroke::new(self.window_dim, self.scale, self.rotation, self.view().scanbox, input_buf);
+        stroke.brush   = self.brush;
+        stroke.blend   = self.blend;
+        stroke.opacity = self.opacity;
+        stroke.color   = self.color;
+        self.strokes.push(stroke);
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PromptKind {
+    /// go to a spot in the world, or a bookmark
+    GoTo,
+
+    /// name a bookmark for the view
+    Bookmark,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Prompt {
+    pub kind: PromptKind,
+    pub text: String,
+}
+
+impl Prompt {
+    pub fn new(kind: PromptKind) -> Prompt {
+        Prompt { kind, text: String::new() }
+    }
+
+    /// What the HUD labels the prompt with
+    pub fn label(&self) -> &'static str {
+        match self.kind {
+            PromptKind::GoTo     => "go to (x, y or bookmark)",
+            PromptKind::Bookmark => "bookmark as",
+        }
+    }
+}
+
+/// The character a key types into a prompt, if it types one
+///
+/// NOTE: the keys we're sent are keys, not text, so this only knows about
+///       the handful which make sense in names & coordinates.
+fn typed_char(key: KeyCode, shift: bool) -> Option<char> {
+    let typed = match key {
+        KeyCode::Key0 | KeyCode::Numpad0 => '0',
+        KeyCode::Key1 | KeyCode::Numpad1 => '1',
+        KeyCode::Key2 | KeyCode::Numpad2 => '2',
+        KeyCode::Key3 | KeyCode::Numpad3 => '3',
+        KeyCode::Key4 | KeyCode::Numpad4 => '4',
+        KeyCode::Key5 | KeyCode::Numpad5 => '5',
+        KeyCode::Key6 | KeyCode::Numpad6 => '6',
+        KeyCode::Key7 | KeyCode::Numpad7 => '7',
+        KeyCode::Key8 | KeyCode::Numpad8 => '8',
+        KeyCode::Key9 | KeyCode::Numpad9 => '9',
+        KeyCode::Minus | KeyCode::Subtract => '-',
+        KeyCode::Period | KeyCode::Decimal => '.',
+        KeyCode::Comma => ',',
+        KeyCode::Space => ' ',
+
+        // NOTE: letters are laid out A to Z in `VirtualKeyCode`
+        key if key as u32 >= KeyCode::A as u32 && key as u32 <= KeyCode::Z as u32 => {
+            let letter = (b'a' + (key as u32 - KeyCode::A as u32) as u8) as char;
+            if shift { letter.to_ascii_uppercase() } else { letter }
+        },
+
+        _ => return None,
+    };
+
+    Some(typed)
+}
+
+/// Rotates a vector (in screen space, where y points down) clockwise on screen
+fn rotate_screen(v: V2f, radians: f32) -> V2f {
+    let (sin, cos) = (radians as f64).sin_cos();
+    V2f(cos * v.0 - sin * v.1, sin * v.0 + cos * v.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use record::{self, Recording};
+
+    fn replay(source: &str) -> Canvas {
+        let recording = Recording::parse(source).expect("test recording is malformed");
+        let mut canvas = Canvas::new(Bindings::defaults());
+        record::replay(&recording, &mut canvas);
+        canvas
+    }
+
+    static DRAG: &str = "
+        0.000 frame
+        0.001 cursor 100 100 -
+        0.002 button pressed left -
+        0.010 frame
+        0.011 cursor 110 100 -
+        0.020 frame
+        0.021 cursor 120 105 -
+        0.030 frame
+        0.031 button released left -
+    ";
+
+    #[test]
+    fn drag_commits_one_stroke() {
+        let canvas = replay(DRAG);
+        assert_eq!(canvas.strokes.len(), 1);
+        assert!(canvas.pending.is_empty());
+
+        // one sample for every frame the button was held
+        let samples = canvas.strokes[0].samples.iter().map(|point| point.screen_xy).collect::<Vec<_>>();
+        assert_eq!(samples, vec![V2(100, 100), V2(110, 100), V2(120, 105)]);
+    }
+
+    #[test]
+    fn stroke_is_pending_until_release() {
+        let canvas = replay(&DRAG[..DRAG.find("0.030 frame").unwrap()]);
+        assert!(canvas.strokes.is_empty());
+        assert_eq!(canvas.pending.len(), 3);
+    }
+
+    #[test]
+    fn release_without_samples_commits_nothing() {
+        let canvas = replay("
+            0.000 frame
+            0.001 button pressed left -
+            0.002 button released left -
+            0.010 frame
+        ");
+
+        assert!(canvas.strokes.is_empty());
+    }
+
+    #[test]
+    fn replay_is_deterministic() {
+        let lhs = replay(DRAG);
+        let rhs = replay(DRAG);
+        assert_eq!(lhs.strokes, rhs.strokes);
+
+        // the mouse can't report pressure, so it's simulated from the timestamps
+        let pressure = lhs.strokes[0].samples.iter().map(|point| point.pressure).collect::<Vec<_>>();
+        assert!(pressure[0] == 1.0 && pressure[1] < 1.0, "pressure: {:?}", pressure);
+    }
+
+    #[test]
+    fn pen_pressure_beats_simulation() {
+        let canvas = replay("
+            0.000 frame
+            0.001 button pressed left -
+            0.002 axis 1 2 32767.5
+            0.002 axis 1 3 0
+            0.002 axis 1 4 0
+            0.010 frame
+            0.011 button released left -
+        ");
+
+        assert_eq!(canvas.strokes[0].samples[0].pressure, 0.5);
+    }
+
+    #[test]
+    fn pens_without_tilt_still_press() {
+        let canvas = replay("
+            0.000 frame
+            0.001 button pressed left -
+            0.002 axis 1 2 16383.75
+            0.010 frame
+            0.011 cursor 10 0 -
+            0.012 axis 1 2 49151.25
+            0.020 frame
+            0.021 button released left -
+        ");
+
+        let samples = &canvas.strokes[0].samples;
+        assert_eq!((samples[0].pressure, samples[1].pressure), (0.25, 0.75));
+        assert!(samples.iter().all(|point| point.tilt.is_none()));
+    }
+
+    #[test]
+    fn mouse_wheels_are_not_pens() {
+        // NOTE: winit reports a mouse's scroll valuators as wheel events, not axes
+        let canvas = replay("
+            0.000 frame
+            0.001 button pressed left -
+            0.002 wheel lines 1 0 -
+            0.003 wheel lines 0 -1 -
+            0.010 frame
+            0.011 cursor 10 0 -
+            0.020 frame
+            0.021 button released left -
+        ");
+
+        let pressure = canvas.strokes[0].samples.iter().map(|point| point.pressure).collect::<Vec<_>>();
+        assert!(pressure[0] == 1.0 && pressure[1] < 1.0, "pressure: {:?}", pressure);
+    }
+
+    #[test]
+    fn pinching_zooms_around_the_fingers() {
+        let canvas = Canvas::new(Bindings::defaults());
+        let (x, y) = render::screen_to_unit(V2f(350.0, 200.0), canvas.window_dim);
+        let before = canvas.view().unapply_world([x, y]);
+
+        // the fingers spread apart around the same spot, off to the upper left
+        let canvas = replay("
+            0.000 frame
+            0.001 touch 1 started 300 200
+            0.002 touch 2 started 400 200
+            0.010 frame
+            0.011 touch 1 moved 250 200
+            0.012 touch 2 moved 450 200
+            0.020 frame
+        ");
+
+        let after = canvas.view().unapply_world([x, y]);
+        assert!((canvas.scale - 2.0).abs() < 1e-3, "zoomed to {}", canvas.scale);
+        assert!((after[0] - before[0]).abs() < 1.0 && (after[1] - before[1]).abs() < 1.0,
+                "{:?} moved to {:?}", before, after);
+    }
+
+    #[test]
+    fn second_finger_cancels_touch_stroke() {
+        let canvas = replay("
+            0.000 frame
+            0.001 touch 1 started 100 100
+            0.010 frame
+            0.011 touch 2 started 200 100
+            0.020 frame
+            0.021 touch 2 moved 220 100
+            0.022 touch 1 ended 100 100
+            0.023 touch 2 ended 220 100
+        ");
+
+        assert!(canvas.strokes.is_empty());
+        assert!(canvas.pending.is_empty());
+
+        // ... & the second finger pinched the view, w/ the spot between the
+        // fingers following them from (150, 100) over to (160, 100)
+        let world = |canvas: &Canvas, pos: V2f| {
+            let (x, y) = render::screen_to_unit(pos, canvas.window_dim);
+            canvas.view().unapply_world([x, y])
+        };
+
+        let (before, after) = (world(&Canvas::new(Bindings::defaults()), V2f(150.0, 100.0)), world(&canvas, V2f(160.0, 100.0)));
+        assert!((canvas.scale - 1.2).abs() < 1e-3, "zoomed to {}", canvas.scale);
+        assert!((after[0] - before[0]).abs() < 1.0 && (after[1] - before[1]).abs() < 1.0, "{:?} moved to {:?}", before, after);
+    }
+
+    #[test]
+    fn quitting_stops_the_replay() {
+        let canvas = replay("
+            0.000 frame
+            0.001 key pressed Escape -
+            0.010 frame
+            0.011 button pressed left -
+            0.020 frame
+            0.021 button released left -
+        ");
+
+        assert!(!canvas.is_running);
+        assert!(canvas.strokes.is_empty());
+    }
+
+    #[test]
+    fn strokes_keep_their_ink() {
+        let canvas = replay("
+            0.000 frame
+            0.001 key pressed LBracket -
+            0.002 key pressed H -
+            0.010 frame
+            0.011 cursor 100 100 -
+            0.012 button pressed left -
+            0.020 frame
+            0.021 button released left -
+        ");
+
+        let stroke = &canvas.strokes[0];
+        assert_eq!(stroke.brush, BrushMode::Highlighter);
+        assert_eq!(stroke.blend, BlendMode::Multiply);
+        assert!((stroke.opacity - 0.9).abs() < 1e-6, "opacity: {}", stroke.opacity);
+
+        // opacity can't go past fully opaque
+        let canvas = replay("
+            0.000 frame
+            0.001 key pressed RBracket -
+        ");
+
+        assert_eq!(canvas.opacity, 1.0);
+    }
+
+    #[test]
+    fn blend_modes_cycle_back_around() {
+        let canvas = replay("
+            0.000 frame
+            0.001 key pressed M -
+            0.010 frame
+            0.011 key released M -
+            0.012 key pressed M -
+            0.020 frame
+            0.021 cursor 100 100 -
+            0.022 button pressed left -
+            0.030 frame
+            0.031 button released left -
+        ");
+
+        assert_eq!(canvas.strokes[0].blend, BlendMode::Screen);
+
+        let mut blend = BlendMode::Normal;
+        for _ in 0..5 { blend = blend.next(); }
+        assert_eq!(blend, BlendMode::Normal);
+    }
+
+    #[test]
+    fn stroke_ends_snap_to_the_grid() {
+        let mut canvas = Canvas::new(Bindings::defaults());
+        canvas.background = Background::Grid;
+        canvas.snapping   = true;
+        record::replay(&Recording::parse(DRAG).unwrap(), &mut canvas);
+
+        // the grid has lines every 32px, crossing the middle of the window
+        let samples = canvas.strokes[0].samples.iter().map(|point| point.screen_xy).collect::<Vec<_>>();
+        assert_eq!(samples, vec![V2(96, 104), V2(110, 100), V2(128, 104)]);
+    }
+
+    #[test]
+    fn strokes_snap_to_the_ends_of_others() {
+        let canvas = replay(&format!("{}
+            0.040 frame
+            0.041 key pressed S -
+            0.050 frame
+            0.051 cursor 124 108 -
+            0.052 button pressed left -
+            0.060 frame
+            0.061 cursor 200 200 -
+            0.070 frame
+            0.071 button released left -
+        ", DRAG));
+
+        assert_eq!(canvas.strokes[1].samples[0].screen_xy, V2(120, 105));
+        assert_eq!(canvas.strokes[1].samples[1].screen_xy, V2(200, 200));
+    }
+
+    #[test]
+    fn shift_keeps_lines_at_fifteen_degrees() {
+        let canvas = replay("
+            0.000 frame
+            0.001 cursor 100 100 -
+            0.002 button pressed left -
+            0.010 frame
+            0.011 cursor 200 120 shift
+            0.020 frame
+            0.021 cursor 160 190 shift
+            0.030 frame
+            0.031 button released left shift
+        ");
+
+        // the pen drifted from ~11 to ~56 degrees, so the whole stroke ends up
+        // on one line at 60 (rather than zigzagging from 15 to 60)
+        let samples = &canvas.strokes[0].samples;
+        assert_eq!(samples.len(), 3);
+        for point in &samples[1..] {
+            let (dx, dy) = ((point.screen_xy.0 - 100) as f32, (point.screen_xy.1 - 100) as f32);
+            let degrees  = dy.atan2(dx).to_degrees();
+            assert!((degrees - 60.0).abs() < 1.0, "{:?} is {} degrees from 100, 100", point.screen_xy, degrees);
+        }
+    }
+
+    #[test]
+    fn clicking_the_minimap_moves_the_view() {
+        let mut canvas = Canvas::new(Bindings::defaults());
+        canvas.minimap = true;
+
+        let minimap = Minimap::new(&canvas);
+        let middle  = (minimap.rect[0] + minimap.rect[2] / 2.0, minimap.rect[1] + minimap.rect[3] / 2.0);
+        let source  = format!("
+            0.000 frame
+            0.001 cursor {} {} -
+            0.002 button pressed left -
+            0.010 frame
+            0.011 cursor {} {} -
+            0.020 frame
+            0.021 button released left -
+        ", middle.0 + 20.0, middle.1, middle.0 + 40.0, middle.1 - 10.0);
+
+        let recording = Recording::parse(&source).expect("test recording is malformed");
+        record::replay(&recording, &mut canvas);
+        assert!(canvas.strokes.is_empty() && canvas.pending.is_empty());
+
+        // the view follows the cursor, w/ the minimap laid out like it was when it was clicked
+        let view   = canvas.view().unapply_world([0.0, 0.0]);
+        let target = minimap.world_at(V2f(middle.0 as f64 + 40.0, middle.1 as f64 - 10.0));
+        assert!((view[0] - target[0]).abs() < 1.0 && (view[1] - target[1]).abs() < 1.0, "{:?} != {:?}", view, target);
+        assert!(target[0] > 0.0 && target[1] > 0.0);
+    }
+
+    #[test]
+    fn zoom_to_fit_frames_every_stroke() {
+        let mut source = DRAG.to_string();
+        source.push_str("
+            0.040 frame
+            0.041 key pressed Left -
+            0.100 frame
+            0.101 key released Left -
+            0.102 key pressed F -
+            0.110 frame
+            0.200 frame
+            0.500 frame
+        ");
+
+        let canvas = replay(&source);
+        let framed = camera::fit(camera::bounds(&canvas.strokes, 0, canvas.window_dim).unwrap(), canvas.window_dim, canvas.camera());
+        assert_eq!(canvas.scale, framed.scale);
+
+        // every sample is on screen, & the drawing is in the middle of it
+        let view = canvas.view();
+        for point in &canvas.strokes[0].samples {
+            let V2f(x, y) = render::unit_to_screen(view.apply_world(canvas.strokes[0].world_pos(point, canvas.window_dim)), canvas.window_dim);
+            assert!(x >= 0.0 && y >= 0.0 && x < 1280.0 && y < 720.0, "{:?} is off screen at {}, {}", point.screen_xy, x, y);
+        }
+
+        let center = canvas.camera().center;
+        assert!((center[0] - framed.center[0]).abs() < 1.0 && (center[1] - framed.center[1]).abs() < 1.0);
+    }
+
+    #[test]
+    fn zoom_to_stroke_frames_the_one_under_the_cursor() {
+        let canvas = replay("
+            0.000 frame
+            0.001 cursor 100 100 -
+            0.002 button pressed left -
+            0.010 frame
+            0.011 cursor 140 100 -
+            0.020 frame
+            0.021 button released left -
+            0.030 frame
+            0.031 cursor 1000 600 -
+            0.032 button pressed left -
+            0.040 frame
+            0.041 cursor 1100 600 -
+            0.050 frame
+            0.051 button released left -
+            0.060 frame
+            0.061 cursor 120 102 -
+            0.062 key pressed F shift
+            0.070 frame
+            0.500 frame
+        ");
+
+        let dim    = canvas.window_dim;
+        let framed = camera::fit(camera::bounds(&canvas.strokes[0..1], 0, dim).unwrap(), dim, Camera::home());
+        assert_eq!(canvas.scale, framed.scale);
+
+        let center = canvas.camera().center;
+        assert!((center[0] - framed.center[0]).abs() < 1.0 && (center[1] - framed.center[1]).abs() < 1.0);
+
+        // pointing at nothing leaves the view alone
+        let missed = replay("
+            0.000 frame
+            0.001 cursor 100 100 -
+            0.002 button pressed left -
+            0.010 frame
+            0.011 button released left -
+            0.020 frame
+            0.021 cursor 600 300 -
+            0.022 key pressed F shift
+            0.500 frame
+        ");
+
+        assert_eq!(missed.camera(), Camera::home());
+    }
+
+    #[test]
+    fn recoloring_paints_the_stroke_under_the_cursor() {
+        let mut canvas = Canvas::new(Bindings::defaults());
+        let recording = Recording::parse(&format!("{}
+            0.040 frame
+            0.041 cursor 300 300 -
+            0.042 button pressed left -
+            0.050 frame
+            0.051 button released left -
+        ", DRAG)).unwrap();
+
+        canvas.color = (10, 20, 30);
+        record::replay(&recording, &mut canvas);
+        assert_eq!(canvas.strokes[0].color, (10, 20, 30));
+
+        // only the stroke under the cursor picks up the new hue
+        canvas.color = (200, 100, 0);
+        let recolor = Recording::parse("
+            0.060 frame
+            0.061 cursor 105 101 -
+            0.062 key pressed C -
+            0.070 frame
+        ").unwrap();
+
+        record::replay(&recolor, &mut canvas);
+        assert_eq!(canvas.strokes[0].color, (200, 100, 0));
+        assert_eq!(canvas.strokes[1].color, (10, 20, 30));
+    }
+
+    #[test]
+    fn go_to_glides_over_to_what_was_typed() {
+        let typed = replay("
+            0.000 frame
+            0.001 key pressed G ctrl
+            0.010 frame
+            0.011 key pressed Minus -
+            0.012 key pressed Key1 -
+            0.013 key pressed Key2 -
+            0.014 key pressed Back -
+            0.015 key pressed Key5 -
+            0.016 key pressed Space -
+            0.017 key pressed Key4 -
+            0.018 key pressed Escape -
+        ");
+
+        // escape closes the prompt, rather than quitting
+        assert!(typed.is_running && typed.prompt.is_none());
+        assert_eq!(typed.scanbox, V2f(0.0, 0.0));
+
+        let canvas = replay("
+            0.000 frame
+            0.001 key pressed G ctrl
+            0.010 frame
+            0.011 key pressed Minus -
+            0.012 key pressed Key1 -
+            0.013 key pressed Key5 -
+            0.014 key pressed Space -
+            0.015 key pressed Key4 -
+            0.020 frame
+            0.021 key pressed Return -
+            0.030 frame
+            0.150 frame
+        ");
+
+        // part way there ...
+        let partway = canvas.camera().center;
+        assert!(canvas.prompt.is_none());
+        assert!(partway[0] < 0.0 && partway[0] > -15.0 && partway[1] > 0.0 && partway[1] < 4.0, "{:?}", partway);
+
+        let mut canvas = canvas;
+        canvas.step(&Frame { at: 1.0, events: vec![] });
+        assert_eq!(canvas.camera().center, [-15.0, 4.0]);
+    }
+
+    #[test]
+    fn small_glides_move_every_frame_and_land_on_the_spot() {
+        // NOTE: zoomed out this far a pixel on the screen is 10 world pixels,
+        //       so this whole glide is less than one of them.
+        let mut canvas = Canvas::new(Bindings::defaults());
+        canvas.scale = 0.05;
+        canvas.move_camera(Camera { center: [7.0, 3.0], ..canvas.camera() });
+
+        let mut last = canvas.camera().center;
+        for frame in 0..=(camera::TWEEN_SECS * 120.0) as usize {
+            canvas.step(&Frame { at: frame as f64 / 120.0, events: vec![] });
+
+            let center = canvas.camera().center;
+            assert!(frame == 0 || center[0] > last[0], "stalled @ {:?} on frame {}", center, frame);
+            last = center;
+        }
+
+        canvas.step(&Frame { at: 1.0, events: vec![] });
+        assert_eq!(canvas.camera().center, [7.0, 3.0]);
+        assert_eq!(canvas.view().scanbox, V2(1, 0));
+    }
+
+    #[test]
+    fn bookmarks_are_named_cycled_and_jumped_to() {
+        let mut canvas = replay("
+            0.000 frame
+            0.001 key pressed B -
+            0.010 frame
+            0.011 key pressed H shift
+            0.012 key pressed I -
+            0.013 key pressed Return -
+        ");
+
+        let there = Camera { center: [40.0, -20.0], ..Camera::home() };
+        canvas.set_camera(there);
+
+        let unnamed = Recording::parse("
+            0.020 frame
+            0.021 key pressed B -
+            0.030 frame
+            0.031 key pressed Return -
+        ").unwrap();
+
+        record::replay(&unnamed, &mut canvas);
+        canvas.set_camera(Camera { center: [-300.0, 100.0], ..Camera::home() });
+
+        // NOTE: "Hi" doesn't take up a number, so the unnamed one is the first
+        let names = canvas.bookmarks.iter().map(|bookmark| &bookmark.name[..]).collect::<Vec<_>>();
+        assert_eq!(names, vec!["Hi", "1"]);
+        assert_eq!(canvas.bookmarks[1].camera, there);
+
+        // cycling from the newest bookmark wraps back around to the first
+        let cycled = Recording::parse("
+            0.100 frame
+            0.101 key pressed N -
+            0.110 frame
+            1.000 frame
+        ").unwrap();
+
+        record::replay(&cycled, &mut canvas);
+        assert_eq!((canvas.bookmark, canvas.camera()), (Some(0), Camera::home()));
+
+        let by_name = Recording::parse("
+            1.100 frame
+            1.101 key pressed G ctrl
+            1.110 frame
+            1.111 key pressed Key1 -
+            1.112 key pressed Return -
+            1.120 frame
+            2.000 frame
+        ").unwrap();
+
+        record::replay(&by_name, &mut canvas);
+        assert_eq!((canvas.bookmark, canvas.camera()), (Some(1), there));
+
+        // a bookmark named like a spot in the world wins out over the spot
+        let spot = Camera { center: [500.0, 500.0], ..Camera::home() };
+        bookmarks::place(&mut canvas.bookmarks, "1 2", spot);
+
+        let spot_name = Recording::parse("
+            2.100 frame
+            2.101 key pressed G ctrl
+            2.110 frame
+            2.111 key pressed Key1 -
+            2.112 key pressed Space -
+            2.113 key pressed Key2 -
+            2.114 key pressed Return -
+            2.120 frame
+            3.000 frame
+        ").unwrap();
+
+        record::replay(&spot_name, &mut canvas);
+        assert_eq!((canvas.bookmark, canvas.camera()), (Some(2), spot));
+    }
+
+    #[test]
+    fn steering_the_view_stops_it_from_gliding() {
+        let mut canvas = Canvas::new(Bindings::defaults());
+        canvas.set_camera(Camera { center: [-200.0, 0.0], ..Camera::home() });
+
+        let recording = Recording::parse("
+            0.000 frame
+            0.001 key pressed Home -
+            0.050 frame
+            0.100 frame
+            0.101 key pressed Up -
+            0.150 frame
+            0.200 frame
+            0.201 key released Up -
+            0.250 frame
+            1.000 frame
+        ").unwrap();
+
+        // it never made it home, & it went up instead
+        record::replay(&recording, &mut canvas);
+        let center = canvas.camera().center;
+        assert!(center[0] < -1.0 && center[0] > -200.0, "ended up at {:?}", center);
+        assert!(center[1] > 1.0, "ended up at {:?}", center);
+    }
+
+    #[test]
+    fn zooming_out_stops_short_of_flipping_over() {
+        let mut source = String::from("
+            0.000 frame
+            0.001 key pressed Minus -
+        ");
+
+        for frame in 1..200 { source.push_str(&format!("{:.2} frame\n", frame as f64 / 10.0)); }
+        assert_eq!(replay(&source).scale, camera::MIN_SCALE);
+    }
+
+    #[test]
+    fn right_dragging_spins_the_view_around_the_middle() {
+        let canvas = replay("
+            0.000 frame
+            0.001 cursor 740 360 -
+            0.002 button pressed right -
+            0.010 frame
+            0.011 cursor 711 431 -
+            0.020 frame
+            0.021 cursor 640 460 -
+            0.030 frame
+            0.031 button released right -
+        ");
+
+        // a quarter turn clockwise, following the cursor
+        assert!((canvas.rotation - std::f32::consts::FRAC_PI_2).abs() < 1e-3, "rotation was {}", canvas.rotation);
+        assert!(canvas.strokes.is_empty() && canvas.pending.is_empty());
+    }
+
+    #[test]
+    fn strokes_stay_under_the_pen_while_the_view_spins() {
+        // the pen holds still while `.` spins the view, then it coasts to a stop
+        let mut source = String::from("
+            0.000 frame
+            0.001 cursor 740 360 -
+            0.002 button pressed left -
+            0.010 frame
+            0.011 key pressed Period -
+        ");
+
+        for frame in 2..60 {
+            source.push_str(&format!("{:.3} frame\n", frame as f64 / 100.0));
+            if frame == 30 { source.push_str("0.301 key released Period -\n"); }
+        }
+
+        source.push_str("0.600 frame\n0.601 button released left -\n");
+        let canvas = replay(&source);
+
+        // so the stroke curls around w/ the paper under it
+        let stroke = &canvas.strokes[0];
+        let dim    = canvas.window_dim;
+        let first  = stroke.world_pos(&stroke.samples[0], dim);
+        let last   = stroke.world_pos(&stroke.samples[stroke.samples.len() - 1], dim);
+        assert!((first[0] - 100.0).abs() < 1.0 && first[1].abs() < 1.0, "started at {:?}", first);
+
+        let turned = last[1].atan2(last[0]).to_degrees();
+        assert!(canvas.rotation.to_degrees() > 20.0);
+        assert!((turned - canvas.rotation.to_degrees()).abs() < 1.0, "ended {} degrees around", turned);
+    }
+
+    #[test]
+    fn bindings_ignore_extra_modifiers_unless_another_chord_wants_them() {
+        // holding shift (i.e: to snap) still pans the view ...
+        let canvas = replay("
+            0.000 frame
+            0.001 key pressed Right shift
+            0.100 frame
+            0.200 frame
+            0.201 key released Right shift
+        ");
+
+        assert!(canvas.scanbox.0 > 0.0, "scanbox: {:?}", canvas.scanbox);
+
+        // ... but shift+N goes back a bookmark & ctrl+G doesn't also change the background
+        let mut canvas = Canvas::new(Bindings::defaults());
+        bookmarks::place(&mut canvas.bookmarks, "a", Camera::home());
+        bookmarks::place(&mut canvas.bookmarks, "b", Camera::home());
+        bookmarks::place(&mut canvas.bookmarks, "c", Camera::home());
+
+        let recording = Recording::parse("
+            0.000 frame
+            0.001 key pressed N shift
+            0.002 key released N shift
+            0.010 frame
+            0.011 key pressed G ctrl
+        ").unwrap();
+
+        record::replay(&recording, &mut canvas);
+        assert_eq!(canvas.bookmark, Some(2));
+        assert_eq!(canvas.background, Background::Solid);
+        assert!(canvas.prompt.is_some());
+    }
+}
@@ -0,0 +1,649 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use rusttype;
+use util;
+
+/// Location & metrics of a single glyph, all measured in pixels.
+#[derive(Copy, Clone, Debug)]
+pub struct Glyph {
+    /// upper left corner of the glyph in the atlas
+    pub atlas_xy: (u32, u32),
+
+    /// size of the glyph's bitmap
+    pub size: (u32, u32),
+
+    /// offset from the pen position to the upper left corner of the bitmap,
+    /// the pen sits at the top of the line so `y` grows downward.
+    pub bearing: (i32, i32),
+
+    /// how far the pen moves after drawing this glyph
+    pub advance: i32,
+}
+
+/// A bitmap font which has been unpacked into a single RGBA atlas.
+///
+/// The atlas is stored top row first, and glyphs are looked up by codepoint.
+/// Bitmap formats which only store coverage (BDF, PSF) are expanded to white
+/// pixels w/ the coverage in the alpha channel.
+pub struct BitmapFont {
+    pub atlas:       Vec<u8>,
+    pub atlas_dim:   (u32, u32),
+    pub line_height: u32,
+    pub baseline:    u32,
+
+    glyphs:  HashMap<char, Glyph>,
+    kerning: HashMap<(char, char), i32>,
+}
+
+/// A scalable TrueType / OpenType font.
+///
+/// Glyphs are rasterized on demand (at whatever size they're drawn) by
+/// the glyph cache in `graphics`.
+pub struct OutlineFont {
+    pub inner: rusttype::Font<'static>,
+}
+
+/// Any font koko knows how to draw.
+///
+/// Text is always drawn at some `size`, in pixels, which is the height
+/// of a single line of text for bitmap fonts & the em height for outlines.
+pub enum Font {
+    Bitmap(BitmapFont),
+    Outline(OutlineFont),
+}
+
+#[derive(Debug)]
+pub enum FontError {
+    Io(io::Error),
+    Malformed(String),
+    Unsupported(String),
+}
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FontError::Io(ref err)          => write!(f, "i/o error reading font: {}", err),
+            FontError::Malformed(ref msg)   => write!(f, "malformed font: {}", msg),
+            FontError::Unsupported(ref msg) => write!(f, "unsupported font: {}", msg),
+        }
+    }
+}
+
+impl Error for FontError {}
+
+impl From<io::Error> for FontError {
+    fn from(err: io::Error) -> FontError { FontError::Io(err) }
+}
+
+impl BitmapFont {
+    /// Looks up the glyph for `cp`, falling back to `?` for characters
+    /// which are not present in the font.
+    pub fn glyph(&self, cp: char) -> Option<&Glyph> {
+        self.glyphs.get(&cp)
+            .or_else(|| self.glyphs.get(&'?'))
+    }
+
+    /// Returns the additional spacing (in pixels) between a pair of characters.
+    pub fn kerning(&self, lhs: char, rhs: char) -> i32 {
+        *self.kerning.get(&(lhs, rhs)).unwrap_or(&0)
+    }
+}
+
+impl OutlineFont {
+    fn scale(&self, size: f32) -> rusttype::Scale { rusttype::Scale::uniform(size) }
+
+    /// Distance from the top of the line to the baseline, in pixels
+    pub fn ascent(&self, size: f32) -> f32 {
+        self.inner.v_metrics(self.scale(size)).ascent
+    }
+}
+
+impl Font {
+    /// Height of a single line of text (in pixels) when drawn at `size`
+    pub fn line_height(&self, size: f32) -> f32 {
+        match *self {
+            Font::Bitmap(_) => size,
+            Font::Outline(ref font) => {
+                let v_metrics = font.inner.v_metrics(font.scale(size));
+                v_metrics.ascent - v_metrics.descent + v_metrics.line_gap
+            },
+        }
+    }
+
+    /// How far the pen moves (in pixels) after drawing `cp` at `size`
+    pub fn advance(&self, cp: char, size: f32) -> f32 {
+        match *self {
+            Font::Bitmap(ref font) => {
+                let scale = size / font.line_height as f32;
+                font.glyph(cp).map_or(0.0, |glyph| glyph.advance as f32 * scale)
+            },
+
+            Font::Outline(ref font) => {
+                font.inner.glyph(cp).scaled(font.scale(size)).h_metrics().advance_width
+            },
+        }
+    }
+
+    /// Additional spacing (in pixels) between a pair of characters drawn at `size`
+    pub fn kerning(&self, lhs: char, rhs: char, size: f32) -> f32 {
+        match *self {
+            Font::Bitmap(ref font) => {
+                font.kerning(lhs, rhs) as f32 * (size / font.line_height as f32)
+            },
+
+            Font::Outline(ref font) => font.inner.pair_kerning(font.scale(size), lhs, rhs),
+        }
+    }
+}
+
+/// Loads a font, picking a parser based on the file extension:
+///
+/// - `.ttf`, `.otf`  => TrueType / OpenType outlines
+/// - `.bdf`          => X11 Glyph Bitmap Distribution Format
+/// - `.psf`, `.psfu` => PC Screen Font (v1 or v2)
+/// - `.fnt`          => AngelCode BMFont (text descriptor w/ TGA pages)
+/// - `.tga`          => koko's original 16x16 cell sprite sheet
+///
+pub fn load_font<P: AsRef<Path>>(path: P) -> Result<Font, FontError> {
+    let path = path.as_ref();
+    let ext  = path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase());
+
+    let bitmap = match ext.as_ref().map(|ext| &ext[..]) {
+        Some("ttf") | Some("otf")  => return load_outline(read_bytes(path)?).map(Font::Outline),
+        Some("bdf")          => load_bdf(&read_text(path)?),
+        Some("psf") | Some("psfu") => load_psf(&read_bytes(path)?),
+        Some("fnt")          => load_bmfont(path),
+        Some("tga")          => load_sprite_sheet(path),
+        _ => Err(FontError::Unsupported(format!("unknown font extension: {:?}", path))),
+    };
+
+    bitmap.map(Font::Bitmap)
+}
+
+/// Parses a TrueType or OpenType font file.
+pub fn load_outline(buf: Vec<u8>) -> Result<OutlineFont, FontError> {
+    rusttype::Font::try_from_vec(buf)
+        .map(|font| OutlineFont { inner: font })
+        .ok_or_else(|| FontError::Malformed("could not parse truetype / opentype font".to_string()))
+}
+
+fn read_bytes(path: &Path) -> Result<Vec<u8>, FontError> {
+    let mut buf = vec![];
+    File::open(path)?.read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+fn read_text(path: &Path) -> Result<String, FontError> {
+    let buf = read_bytes(path)?;
+    String::from_utf8(buf)
+        .map_err(|_| FontError::Malformed(format!("{:?} is not valid utf-8", path)))
+}
+
+
+/// Packs coverage bitmaps into rows ("shelves") of a fixed width atlas.
+struct AtlasBuilder {
+    width:   u32,
+    cursor:  (u32, u32),
+    shelf_h: u32,
+    pixels:  Vec<u8>,
+}
+
+impl AtlasBuilder {
+    /// Sizes the atlas so that `count` cells of `cell` pixels end up roughly square.
+    fn new(count: usize, cell: (u32, u32)) -> AtlasBuilder {
+        let cols  = (count as f64).sqrt().ceil() as u32;
+        let width = ((cell.0 + 1) * cols.max(1)).next_power_of_two().max(64);
+
+        AtlasBuilder { width, cursor: (0, 0), shelf_h: 0, pixels: vec![] }
+    }
+
+    /// Copies a `w * h` coverage bitmap into the atlas and returns its position.
+    fn insert(&mut self, w: u32, h: u32, coverage: &[u8]) -> (u32, u32) {
+        if self.cursor.0 + w > self.width {
+            self.cursor  = (0, self.cursor.1 + self.shelf_h + 1);
+            self.shelf_h = 0;
+        }
+
+        let (x, y) = self.cursor;
+        let rows   = (y + h) as usize;
+        let needed = rows * self.width as usize * 4;
+        if self.pixels.len() < needed { self.pixels.resize(needed, 0x00); }
+
+        for row in 0..h {
+            for col in 0..w {
+                let alpha  = coverage[(row * w + col) as usize];
+                let px_ofs = (((y + row) * self.width + (x + col)) * 4) as usize;
+                self.pixels[px_ofs..px_ofs + 4].copy_from_slice(&[0xFF, 0xFF, 0xFF, alpha]);
+            }
+        }
+
+        self.cursor.0 += w + 1;
+        self.shelf_h   = self.shelf_h.max(h);
+        (x, y)
+    }
+
+    fn finish(mut self) -> (Vec<u8>, (u32, u32)) {
+        let height = ((self.pixels.len() / 4) as u32 / self.width).max(1);
+        self.pixels.resize((self.width * height * 4) as usize, 0x00);
+        (self.pixels, (self.width, height))
+    }
+}
+
+
+/// Parses a BDF font, see: Adobe's "Glyph Bitmap Distribution Format" v2.1
+pub fn load_bdf(source: &str) -> Result<BitmapFont, FontError> {
+    struct BdfChar { cp: char, dwidth: i32, bbx: (u32, u32, i32, i32), coverage: Vec<u8> }
+
+    let malformed = |msg: &str| FontError::Malformed(format!("bdf: {}", msg));
+    let num = |tok: Option<&str>| -> Result<i32, FontError> {
+        tok.and_then(|tok| tok.parse().ok()).ok_or_else(|| malformed("expected a number"))
+    };
+
+    let mut font_bbx = (0, 0, 0, 0);
+    let mut ascent   = None;
+    let mut descent  = None;
+    let mut chars    = vec![];
+
+    let mut lines = source.lines();
+    while let Some(line) = lines.next() {
+        let mut toks = line.split_whitespace();
+        match toks.next() {
+            Some("FONTBOUNDINGBOX") => {
+                font_bbx = (num(toks.next())?, num(toks.next())?, num(toks.next())?, num(toks.next())?);
+            },
+
+            Some("FONT_ASCENT")  => ascent  = Some(num(toks.next())?),
+            Some("FONT_DESCENT") => descent = Some(num(toks.next())?),
+
+            Some("STARTCHAR") => {
+                let mut encoding = -1;
+                let mut dwidth   = font_bbx.0;
+                let mut bbx      = (font_bbx.0 as u32, font_bbx.1 as u32, font_bbx.2, font_bbx.3);
+                let mut coverage = vec![];
+
+                while let Some(line) = lines.next() {
+                    let mut toks = line.split_whitespace();
+                    match toks.next() {
+                        Some("ENCODING") => encoding = num(toks.next())?,
+                        Some("DWIDTH")   => dwidth   = num(toks.next())?,
+                        Some("BBX") => {
+                            bbx = (num(toks.next())? as u32, num(toks.next())? as u32,
+                                   num(toks.next())?,        num(toks.next())?);
+                        },
+
+                        Some("BITMAP") => {
+                            coverage.reserve((bbx.0 * bbx.1) as usize);
+                            for _ in 0..bbx.1 {
+                                let row  = lines.next().ok_or_else(|| malformed("truncated bitmap"))?;
+                                let bits = u64::from_str_radix(row.trim(), 16)
+                                    .map_err(|_| malformed("bitmap rows must be hex"))?;
+
+                                // rows are padded out to a whole number of bytes
+                                let row_bits = row.trim().len() as u32 * 4;
+                                if row_bits < bbx.0 || row_bits > 64 {
+                                    return Err(malformed("bitmap row does not match glyph width"));
+                                }
+
+                                for col in 0..bbx.0 {
+                                    let lit = (bits >> (row_bits - 1 - col)) & 1 == 1;
+                                    coverage.push(if lit { 0xFF } else { 0x00 });
+                                }
+                            }
+                        },
+
+                        Some("ENDCHAR") => break,
+                        _ => {},
+                    }
+                }
+
+                // NOTE: encoding -1 is a glyph w/o a standard codepoint
+                if let Some(cp) = ::std::char::from_u32(encoding as u32).filter(|_| encoding >= 0) {
+                    chars.push(BdfChar { cp, dwidth, bbx, coverage });
+                }
+            },
+
+            _ => {},
+        }
+    }
+
+    if chars.is_empty() { return Err(malformed("font has no glyphs")); }
+
+    // the font's bounding box is measured from the baseline, so when the
+    // font doesn't carry explicit properties: derive them from its extents
+    let ascent  = ascent.unwrap_or(font_bbx.1 + font_bbx.3);
+    let descent = descent.unwrap_or(-font_bbx.3);
+
+    let mut atlas  = AtlasBuilder::new(chars.len(), (font_bbx.0 as u32, font_bbx.1 as u32));
+    let mut glyphs = HashMap::new();
+    for ch in &chars {
+        let (w, h, ofs_x, ofs_y) = ch.bbx;
+        glyphs.insert(ch.cp, Glyph {
+            atlas_xy: atlas.insert(w, h, &ch.coverage),
+            size:     (w, h),
+            bearing:  (ofs_x, ascent - (ofs_y + h as i32)),
+            advance:  ch.dwidth,
+        });
+    }
+
+    let (atlas, atlas_dim) = atlas.finish();
+    Ok(BitmapFont {
+        atlas,
+        atlas_dim,
+        line_height: (ascent + descent) as u32,
+        baseline:    ascent as u32,
+
+        glyphs,
+        kerning: HashMap::new(),
+    })
+}
+
+
+/// Parses a PC Screen Font, both the original (v1) and extended (v2) formats.
+///
+/// Fonts w/o a unicode table are assumed to be laid out in Latin-1 order.
+pub fn load_psf(buf: &[u8]) -> Result<BitmapFont, FontError> {
+    let malformed = |msg: &str| FontError::Malformed(format!("psf: {}", msg));
+    #[allow(clippy::identity_op)]
+    let read_u32  = |ofs: usize| -> u32 {
+        (buf[ofs + 0] as u32)       | (buf[ofs + 1] as u32) <<  8 |
+        (buf[ofs + 2] as u32) << 16 | (buf[ofs + 3] as u32) << 24
+    };
+
+    // (glyph count, bytes per glyph, width, height, header size, has unicode table, is v2)
+    let (count, charsize, width, height, header, has_table, is_v2) = match buf {
+        [0x36, 0x04, mode, charsize, ..] => {
+            let count = if mode & 0x01 != 0 { 512 } else { 256 };
+            (count, *charsize as usize, 8, *charsize as u32, 4, mode & 0x06 != 0, false)
+        },
+
+        [0x72, 0xb5, 0x4a, 0x86, ..] if buf.len() >= 32 => {
+            let flags = read_u32(12);
+            (read_u32(16) as usize, read_u32(20) as usize,
+             read_u32(28), read_u32(24), read_u32(8) as usize, flags & 0x01 != 0, true)
+        },
+
+        _ => return Err(malformed("bad magic number")),
+    };
+
+    let table_ofs = header + count * charsize;
+    if buf.len() < table_ofs { return Err(malformed("truncated glyph data")); }
+
+    // figure out which codepoints map to each glyph
+    let mut mapping: Vec<Vec<char>> = vec![vec![]; count];
+    if has_table && is_v2 {
+        let mut entries = buf[table_ofs..].split(|&byte| byte == 0xFF);
+        for cps in mapping.iter_mut() {
+            let entry = entries.next().unwrap_or(&[]);
+
+            // anything after 0xFE is a combining sequence; which we can't render anyways
+            let singles = entry.split(|&byte| byte == 0xFE).next().unwrap_or(&[]);
+            cps.extend(String::from_utf8_lossy(singles).chars());
+        }
+    } else if has_table {
+        let mut ofs = table_ofs;
+        for cps in mapping.iter_mut() {
+            let mut in_sequence = false;
+            while ofs + 1 < buf.len() {
+                let code = (buf[ofs] as u32) | (buf[ofs + 1] as u32) << 8;
+                ofs += 2;
+
+                match code {
+                    0xFFFF => break,
+                    0xFFFE => in_sequence = true,
+                    code if !in_sequence => cps.extend(::std::char::from_u32(code)),
+                    _ => {},
+                }
+            }
+        }
+    } else {
+        for (idx, cps) in mapping.iter_mut().enumerate() {
+            cps.extend(::std::char::from_u32(idx as u32));
+        }
+    }
+
+    let pitch = width.div_ceil(8) as usize;
+    let mut atlas  = AtlasBuilder::new(count, (width, height));
+    let mut glyphs = HashMap::new();
+    for (idx, cps) in mapping.iter().enumerate() {
+        if cps.is_empty() { continue }
+
+        let data = &buf[header + idx * charsize..header + (idx + 1) * charsize];
+        let mut coverage = Vec::with_capacity((width * height) as usize);
+        for row in 0..height as usize {
+            for col in 0..width as usize {
+                let lit = data[row * pitch + col / 8] & (0x80 >> (col % 8)) != 0;
+                coverage.push(if lit { 0xFF } else { 0x00 });
+            }
+        }
+
+        let glyph = Glyph {
+            atlas_xy: atlas.insert(width, height, &coverage),
+            size:     (width, height),
+            bearing:  (0, 0),
+            advance:  width as i32,
+        };
+
+        for &cp in cps { glyphs.insert(cp, glyph); }
+    }
+
+    let (atlas, atlas_dim) = atlas.finish();
+    Ok(BitmapFont {
+        atlas,
+        atlas_dim,
+        line_height: height,
+        baseline:    height,
+
+        glyphs,
+        kerning: HashMap::new(),
+    })
+}
+
+
+/// Parses an AngelCode BMFont text descriptor & loads its pages.
+///
+/// NOTE: pages must be uncompressed TGA images, they're stacked vertically
+///       so that the whole font lives in a single atlas.
+pub fn load_bmfont(path: &Path) -> Result<BitmapFont, FontError> {
+    let malformed = |msg: String| FontError::Malformed(format!("bmfont: {}", msg));
+    let source    = read_text(path)?;
+    let base_dir  = path.parent().unwrap_or_else(|| Path::new("."));
+
+    let mut line_height = 0;
+    let mut baseline    = 0;
+    let mut pages       = vec![];
+    let mut chars       = vec![];
+    let mut kerning     = HashMap::new();
+
+    for line in source.lines() {
+        let (tag, attrs) = parse_bmfont_line(line);
+        let attr = |key: &str| -> Result<i32, FontError> {
+            attrs.get(key)
+                .and_then(|val| val.parse().ok())
+                .ok_or_else(|| malformed(format!("`{}` is missing attribute `{}`", tag, key)))
+        };
+
+        match tag {
+            "common" => {
+                line_height = attr("lineHeight")? as u32;
+                baseline    = attr("base")? as u32;
+            },
+
+            "page" => {
+                let file = attrs.get("file")
+                    .ok_or_else(|| malformed("page is missing a file".to_string()))?;
+
+                pages.push((attr("id")?, base_dir.join(file)));
+            },
+
+            "char" => {
+                let cp = ::std::char::from_u32(attr("id")? as u32)
+                    .ok_or_else(|| malformed("char id is not a valid codepoint".to_string()))?;
+
+                chars.push((cp, attr("page").unwrap_or(0), Glyph {
+                    atlas_xy: (attr("x")? as u32, attr("y")? as u32),
+                    size:     (attr("width")? as u32, attr("height")? as u32),
+                    bearing:  (attr("xoffset")?, attr("yoffset")?),
+                    advance:  attr("xadvance")?,
+                }));
+            },
+
+            "kerning" => {
+                let lhs = ::std::char::from_u32(attr("first")? as u32);
+                let rhs = ::std::char::from_u32(attr("second")? as u32);
+                if let (Some(lhs), Some(rhs)) = (lhs, rhs) {
+                    kerning.insert((lhs, rhs), attr("amount")?);
+                }
+            },
+
+            _ => {},
+        }
+    }
+
+    if pages.is_empty() { return Err(malformed("font has no pages".to_string())); }
+    pages.sort_by_key(|&(id, _)| id);
+
+    // stack the pages on top of each other
+    let mut atlas     = vec![];
+    let mut atlas_dim = (0, 0);
+    let mut page_ofs  = HashMap::new();
+    for (id, page_path) in pages {
+        let is_tga = page_path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("tga"));
+        if !is_tga {
+            return Err(FontError::Unsupported(format!("bmfont page must be a tga: {:?}", page_path)));
+        }
+
+        let (image, (width, height)) = util::load_image_tga(&page_path)?;
+        if atlas_dim.0 != 0 && atlas_dim.0 != width as u32 {
+            return Err(malformed("all pages must be the same width".to_string()));
+        }
+
+        page_ofs.insert(id, atlas_dim.1);
+        atlas.extend_from_slice(&image);
+        atlas_dim = (width as u32, atlas_dim.1 + height as u32);
+    }
+
+    let mut glyphs = HashMap::new();
+    for (cp, page, mut glyph) in chars {
+        glyph.atlas_xy.1 += *page_ofs.get(&page)
+            .ok_or_else(|| malformed(format!("char {:?} refers to missing page {}", cp, page)))?;
+
+        glyphs.insert(cp, glyph);
+    }
+
+    Ok(BitmapFont {
+        atlas,
+        atlas_dim,
+        line_height,
+        baseline,
+
+        glyphs,
+        kerning,
+    })
+}
+
+/// Splits a BMFont line into its tag & `key=value` attributes, values may be quoted.
+fn parse_bmfont_line(line: &str) -> (&str, HashMap<&str, &str>) {
+    let line = line.trim();
+    let (tag, mut rest) = match line.find(char::is_whitespace) {
+        Some(idx) => (&line[..idx], line[idx..].trim_start()),
+        None      => (line, ""),
+    };
+
+    let mut attrs = HashMap::new();
+    while let Some(eq) = rest.find('=') {
+        let key = rest[..eq].trim();
+        let val = &rest[eq + 1..];
+
+        let (val, tail) = if let Some(quoted) = val.strip_prefix('"') {
+            let end = quoted.find('"').unwrap_or(quoted.len());
+            (&quoted[..end], &quoted[(end + 1).min(quoted.len())..])
+        } else {
+            let end = val.find(char::is_whitespace).unwrap_or(val.len());
+            (&val[..end], &val[end..])
+        };
+
+        attrs.insert(key, val);
+        rest = tail.trim_start();
+    }
+
+    (tag, attrs)
+}
+
+
+/// Loads koko's original font: a TGA sprite sheet w/ sixteen square cells per row.
+///
+/// The cell size is derived from the image width; the character layout is
+/// fixed to match `simple-font.tga`.
+pub fn load_sprite_sheet(path: &Path) -> Result<BitmapFont, FontError> {
+    let (image, (width, height)) = util::load_image_tga(path)?;
+    let cell = (width / 16) as u32;
+    if cell == 0 || height % cell as usize != 0 {
+        return Err(FontError::Malformed(format!("sprite sheet {:?} is not a 16 column grid", path)));
+    }
+
+    let mut glyphs = HashMap::new();
+    for cp in (0x20u8..0x7F).map(|cp| cp as char) {
+        if let Some((col, row)) = sprite_sheet_cell(cp) {
+            if (row + 1) * cell > height as u32 { continue }
+
+            glyphs.insert(cp, Glyph {
+                atlas_xy: (col * cell, row * cell),
+                size:     (cell, cell),
+                bearing:  (0, 0),
+                advance:  cell as i32,
+            });
+        }
+    }
+
+    Ok(BitmapFont {
+        atlas:       image,
+        atlas_dim:   (width as u32, height as u32),
+        line_height: cell,
+        baseline:    cell,
+
+        glyphs,
+        kerning: HashMap::new(),
+    })
+}
+
+/// Maps a character to its (column, row) in the sprite sheet.
+///
+/// NOTE: currently only covers the subset of ASCII present in `simple-font.tga`
+fn sprite_sheet_cell(cp: char) -> Option<(u32, u32)> {
+    let cell = match cp {
+        'A'..='P' => (cp as u32 - 'A' as u32,      0),
+        'Q'..='Z' => (cp as u32 - 'Q' as u32,      1),
+        'a'..='f' => (cp as u32 - 'a' as u32 + 10, 1),
+        'g'..='v' => (cp as u32 - 'g' as u32,      2),
+        'w'..='z' => (cp as u32 - 'w' as u32,      3),
+
+        '1'..='9'  => ((cp  as u32 - '1' as u32) + 4, 3),
+        '0'        => (('9' as u32 - '0' as u32) + 4, 3),
+
+        ' ' => ( 0, 7),
+        '-' => (14, 3),
+        ',' => ( 0, 4),
+        '.' => ( 1, 4),
+        '@' => ( 5, 4),
+        '#' => ( 6, 4),
+        '(' => (12, 4),
+        ')' => (13, 4),
+        '=' => (15, 4),
+        ':' => ( 2, 5),
+        '[' => ( 8, 5),
+        ']' => ( 9, 5),
+        '<' => (14, 5),
+        '>' => (15, 5),
+
+        _ => return None,
+    };
+
+    Some(cell)
+}
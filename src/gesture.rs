@@ -0,0 +1,337 @@
+use std::f64::consts::PI;
+
+use glium::glutin::TouchPhase;
+
+use units::V2f;
+
+/// Something the user did w/ their fingers, as recognized from touch events.
+///
+/// All positions & distances are in pixels from the upper left of the window.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Gesture {
+    /// a single finger touched down & started drawing a stroke
+    StrokeBegin(V2f),
+    StrokeMove(V2f),
+    StrokeEnd,
+
+    /// the stroke turned out to be the start of a two finger gesture
+    StrokeCancel,
+
+    /// two fingers moved together by this much
+    Pan(V2f),
+
+    /// two fingers moved apart (`factor > 1.0`) or together around `center`
+    Zoom { factor: f64, center: V2f },
+
+    /// two fingers turned clockwise (on screen) by this many radians around `center`
+    Rotate { radians: f64, center: V2f },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Mode {
+    Idle,
+    Drawing(u64),
+
+    /// two fingers are down, and were last seen like so
+    Transform { center: V2f, dist: f64, angle: f64 },
+
+    /// a gesture ended w/ fingers still down, they're ignored until lifted
+    Finishing,
+}
+
+/// Turns a stream of raw touch events into strokes & view gestures.
+///
+/// One finger draws, a second finger cancels the stroke & starts a gesture
+/// which pans, zooms & rotates all at once based on how the first two
+/// fingers move relative to each other. Any extra fingers are ignored.
+pub struct GestureRecognizer {
+    touches: Vec<(u64, V2f)>, // in the order they touched down
+    mode:    Mode,
+}
+
+impl GestureRecognizer {
+    pub fn new() -> GestureRecognizer {
+        GestureRecognizer { touches: vec![], mode: Mode::Idle }
+    }
+
+    /// Feeds the recognizer a touch event for finger `id` at `pos`,
+    /// responds w/ any gestures it completed.
+    pub fn touch_event(&mut self, id: u64, phase: TouchPhase, pos: V2f) -> Vec<Gesture> {
+        let mut gestures = vec![];
+
+        match phase {
+            TouchPhase::Started => {
+                self.touches.retain(|&(touch_id, _)| touch_id != id);
+                self.touches.push((id, pos));
+
+                match (self.mode, self.touches.len()) {
+                    (Mode::Idle, 1) => {
+                        self.mode = Mode::Drawing(id);
+                        gestures.push(Gesture::StrokeBegin(pos));
+                    },
+
+                    (Mode::Drawing(_), 2) => {
+                        gestures.push(Gesture::StrokeCancel);
+                        self.mode = self.transform();
+                    },
+
+                    _ => {},
+                }
+            },
+
+            TouchPhase::Moved => {
+                match self.touches.iter_mut().find(|&&mut (touch_id, _)| touch_id == id) {
+                    Some(touch) => touch.1 = pos,
+                    None => return gestures,
+                }
+
+                match self.mode {
+                    Mode::Drawing(stroke_id) if stroke_id == id => gestures.push(Gesture::StrokeMove(pos)),
+
+                    Mode::Transform { center, dist, angle } if self.is_gesture_finger(id) => {
+                        let next = self.transform();
+                        if let Mode::Transform { center: next_center, dist: next_dist, angle: next_angle } = next {
+                            let pan = V2f(next_center.0 - center.0, next_center.1 - center.1);
+                            if pan.0 != 0.0 || pan.1 != 0.0 {
+                                gestures.push(Gesture::Pan(pan));
+                            }
+
+                            // NOTE: fingers on top of each other have no meaningful scale
+                            if dist > 0.0 && next_dist > 0.0 && next_dist != dist {
+                                gestures.push(Gesture::Zoom { factor: next_dist / dist, center: next_center });
+                            }
+
+                            let radians = wrap_angle(next_angle - angle);
+                            if radians != 0.0 {
+                                gestures.push(Gesture::Rotate { radians, center: next_center });
+                            }
+                        }
+
+                        self.mode = next;
+                    },
+
+                    _ => {},
+                }
+            },
+
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                let was_gesture_finger = self.is_gesture_finger(id);
+                self.touches.retain(|&(touch_id, _)| touch_id != id);
+
+                match self.mode {
+                    Mode::Drawing(stroke_id) if stroke_id == id => {
+                        gestures.push(match phase {
+                            TouchPhase::Ended => Gesture::StrokeEnd,
+                            _                 => Gesture::StrokeCancel,
+                        });
+
+                        self.mode = Mode::Idle;
+                    },
+
+                    // carry on w/ the next finger in line, if there is one
+                    Mode::Transform { .. } if was_gesture_finger => {
+                        self.mode = if self.touches.len() >= 2 { self.transform() } else { Mode::Finishing };
+                    },
+
+                    _ => {},
+                }
+
+                if self.touches.is_empty() { self.mode = Mode::Idle; }
+            },
+        }
+
+        gestures
+    }
+
+    fn is_gesture_finger(&self, id: u64) -> bool {
+        self.touches.iter().take(2).any(|&(touch_id, _)| touch_id == id)
+    }
+
+    /// Measures the first two fingers
+    fn transform(&self) -> Mode {
+        let (lhs, rhs) = (self.touches[0].1, self.touches[1].1);
+        let delta = V2f(rhs.0 - lhs.0, rhs.1 - lhs.1);
+
+        Mode::Transform {
+            center: V2f((lhs.0 + rhs.0) / 2.0, (lhs.1 + rhs.1) / 2.0),
+            dist:   delta.length(),
+            angle:  delta.1.atan2(delta.0),
+        }
+    }
+}
+
+impl Default for GestureRecognizer {
+    fn default() -> GestureRecognizer { GestureRecognizer::new() }
+}
+
+/// Wraps an angle into `-PI ..= PI`, so fingers crossing the x-axis don't spin the view
+fn wrap_angle(radians: f64) -> f64 {
+    let mut radians = radians % (2.0 * PI);
+    if radians >  PI { radians -= 2.0 * PI; }
+    if radians < -PI { radians += 2.0 * PI; }
+    radians
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glium::glutin::TouchPhase::*;
+
+    fn close(lhs: f64, rhs: f64) -> bool { (lhs - rhs).abs() < 1e-9 }
+
+    #[test]
+    fn one_finger_draws_a_stroke() {
+        let mut touch = GestureRecognizer::new();
+        assert_eq!(touch.touch_event(1, Started, V2f(10.0, 10.0)), vec![Gesture::StrokeBegin(V2f(10.0, 10.0))]);
+        assert_eq!(touch.touch_event(1, Moved,   V2f(12.0, 11.0)), vec![Gesture::StrokeMove(V2f(12.0, 11.0))]);
+        assert_eq!(touch.touch_event(1, Ended,   V2f(12.0, 11.0)), vec![Gesture::StrokeEnd]);
+
+        // and the next touch starts a fresh one
+        assert_eq!(touch.touch_event(2, Started, V2f(0.0, 0.0)), vec![Gesture::StrokeBegin(V2f(0.0, 0.0))]);
+    }
+
+    #[test]
+    fn cancelled_touch_cancels_the_stroke() {
+        let mut touch = GestureRecognizer::new();
+        touch.touch_event(1, Started, V2f(10.0, 10.0));
+        assert_eq!(touch.touch_event(1, Cancelled, V2f(10.0, 10.0)), vec![Gesture::StrokeCancel]);
+    }
+
+    #[test]
+    fn second_finger_cancels_the_stroke() {
+        let mut touch = GestureRecognizer::new();
+        touch.touch_event(1, Started, V2f(10.0, 10.0));
+        assert_eq!(touch.touch_event(2, Started, V2f(50.0, 10.0)), vec![Gesture::StrokeCancel]);
+
+        // the first finger is part of the gesture now, not a stroke
+        assert_eq!(touch.touch_event(1, Ended, V2f(10.0, 10.0)), vec![]);
+    }
+
+    #[test]
+    fn two_finger_drag_pans() {
+        let mut touch = GestureRecognizer::new();
+        touch.touch_event(1, Started, V2f(10.0, 10.0));
+        touch.touch_event(2, Started, V2f(50.0, 10.0));
+
+        // fingers only move one at a time, so the view wobbles in between
+        let (mut pan, mut factor, mut radians) = (V2f(0.0, 0.0), 1.0, 0.0);
+        for (id, pos) in [(1, V2f(20.0, 30.0)), (2, V2f(60.0, 30.0))].iter() {
+            for gesture in touch.touch_event(*id, Moved, *pos) {
+                match gesture {
+                    Gesture::Pan(delta)            => pan = V2f(pan.0 + delta.0, pan.1 + delta.1),
+                    Gesture::Zoom { factor: f, .. } => factor *= f,
+                    Gesture::Rotate { radians: r, .. } => radians += r,
+                    other => panic!("unexpected gesture: {:?}", other),
+                }
+            }
+        }
+
+        assert_eq!(pan, V2f(10.0, 20.0));
+        assert!(close(factor, 1.0) && close(radians, 0.0), "zoomed {}, rotated {}", factor, radians);
+    }
+
+    #[test]
+    fn pinch_zooms_around_the_center() {
+        let mut touch = GestureRecognizer::new();
+        touch.touch_event(1, Started, V2f(40.0, 0.0));
+        touch.touch_event(2, Started, V2f(60.0, 0.0));
+
+        // spreading both fingers evenly doubles the distance w/o panning
+        assert_eq!(touch.touch_event(1, Moved, V2f(30.0, 0.0)), vec![
+            Gesture::Pan(V2f(-5.0, 0.0)),
+            Gesture::Zoom { factor: 1.5, center: V2f(45.0, 0.0) },
+        ]);
+
+        let gestures = touch.touch_event(2, Moved, V2f(70.0, 0.0));
+        assert_eq!(gestures[0], Gesture::Pan(V2f(5.0, 0.0)));
+        match gestures[1] {
+            Gesture::Zoom { factor, center } => {
+                assert!(close(factor * 1.5, 2.0), "zoomed by {}", factor * 1.5);
+                assert_eq!(center, V2f(50.0, 0.0));
+            },
+
+            other => panic!("unexpected gesture: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn pinch_reports_total_zoom() {
+        let mut touch = GestureRecognizer::new();
+        touch.touch_event(1, Started, V2f(40.0, 0.0));
+        touch.touch_event(2, Started, V2f(60.0, 0.0));
+
+        let mut factor = 1.0;
+        for (id, pos) in [(1, V2f(30.0, 0.0)), (2, V2f(70.0, 0.0)), (1, V2f(10.0, 0.0))].iter() {
+            for gesture in touch.touch_event(*id, Moved, *pos) {
+                if let Gesture::Zoom { factor: step, .. } = gesture { factor *= step; }
+            }
+        }
+
+        assert!(close(factor, 3.0), "zoomed by {}", factor);
+    }
+
+    #[test]
+    fn two_finger_twist_rotates() {
+        let mut touch = GestureRecognizer::new();
+        touch.touch_event(1, Started, V2f(-10.0, 0.0));
+        touch.touch_event(2, Started, V2f( 10.0, 0.0));
+
+        // swing the second finger a quarter turn clockwise (on screen) around the first
+        let gestures = touch.touch_event(2, Moved, V2f(-10.0, 20.0));
+        let radians = gestures.iter().filter_map(|gesture| match *gesture {
+            Gesture::Rotate { radians, .. } => Some(radians),
+            _ => None,
+        }).sum::<f64>();
+
+        assert!(close(radians, PI / 2.0), "rotated by {}", radians);
+    }
+
+    #[test]
+    fn rotation_wraps_across_the_x_axis() {
+        let mut touch = GestureRecognizer::new();
+        touch.touch_event(1, Started, V2f(0.0,  0.0));
+        touch.touch_event(2, Started, V2f(-10.0, -1.0));
+
+        // a tiny turn which crosses from -PI to PI shouldn't spin all the way around
+        for gesture in touch.touch_event(2, Moved, V2f(-10.0, 1.0)) {
+            if let Gesture::Rotate { radians, .. } = gesture {
+                assert!(radians.abs() < 0.5, "rotated by {}", radians);
+            }
+        }
+    }
+
+    #[test]
+    fn extra_fingers_are_ignored() {
+        let mut touch = GestureRecognizer::new();
+        touch.touch_event(1, Started, V2f(0.0,  0.0));
+        touch.touch_event(2, Started, V2f(10.0, 0.0));
+        assert_eq!(touch.touch_event(3, Started, V2f(99.0, 99.0)), vec![]);
+        assert_eq!(touch.touch_event(3, Moved,   V2f(0.0,  0.0)), vec![]);
+    }
+
+    #[test]
+    fn lifting_a_gesture_finger_hands_off_to_the_next() {
+        let mut touch = GestureRecognizer::new();
+        touch.touch_event(1, Started, V2f(0.0,  0.0));
+        touch.touch_event(2, Started, V2f(10.0, 0.0));
+        touch.touch_event(3, Started, V2f(20.0, 0.0));
+        touch.touch_event(1, Ended,   V2f(0.0,  0.0));
+
+        // fingers 2 & 3 carry on the gesture
+        assert_eq!(touch.touch_event(3, Moved, V2f(20.0, 10.0)).len(), 3);
+    }
+
+    #[test]
+    fn leftover_finger_does_not_draw() {
+        let mut touch = GestureRecognizer::new();
+        touch.touch_event(1, Started, V2f(0.0,  0.0));
+        touch.touch_event(2, Started, V2f(10.0, 0.0));
+        touch.touch_event(2, Ended,   V2f(10.0, 0.0));
+
+        assert_eq!(touch.touch_event(1, Moved, V2f(5.0, 5.0)), vec![]);
+        assert_eq!(touch.touch_event(1, Ended, V2f(5.0, 5.0)), vec![]);
+
+        // until every finger has lifted
+        assert_eq!(touch.touch_event(1, Started, V2f(0.0, 0.0)), vec![Gesture::StrokeBegin(V2f(0.0, 0.0))]);
+    }
+}
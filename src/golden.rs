@@ -0,0 +1,286 @@
+//! Golden image tests: scripted input is replayed onto a canvas, drawn w/
+//! the software renderer & compared against a reference image.
+//!
+//! Each case is a recording (`tests/golden/<name>.rec`) w/ a reference image
+//! next to it (`tests/golden/<name>.tga`.) When a case doesn't match, the
+//! frame it drew & a diff against the reference (mismatched pixels in red
+//! over a faded copy of the reference) are written to `target/golden/`.
+//!
+//! After an intentional change to how things are drawn: look over the diffs,
+//! then rewrite the references w/ `KOKO_BLESS=1 cargo test golden`.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use background::Background;
+use bindings::Bindings;
+use canvas::{Canvas, ControlPoint, Stroke};
+use font;
+use lod;
+use record::{self, Recording};
+use render::{self, Renderer, View, COLOR_CLEAR};
+use soft::SoftRenderer;
+use text::{self, Align, Anchor, Span, TextPaint, TextStyle};
+use units::V2;
+use util;
+
+/// How far apart (`0.0 ..= 1.0`) two pixels can be before they're different
+static PIXEL_THRESHOLD: f64 = 0.1;
+
+/// Fraction of the pixels in an image which are allowed to be different
+static MAX_MISMATCH: f64 = 0.001;
+
+/// The largest possible `perceptual_delta`, between black & white
+static MAX_DELTA: f64 = 35215.0;
+
+/// How different two (opaque) pixels look, weighing brightness above hue.
+///
+/// NOTE: this is the squared distance between the colors in the YIQ color
+///       space, see: Kotsarenko & Ramos' "Measuring perceived color difference
+///       using YIQ NTSC transmission color space in mobile applications"
+fn perceptual_delta(lhs: &[u8], rhs: &[u8]) -> f64 {
+    let yiq = |px: &[u8]| {
+        let (r, g, b) = (px[0] as f64, px[1] as f64, px[2] as f64);
+        (r * 0.29889531 + g * 0.58662247 + b * 0.11448223,
+         r * 0.59597799 - g * 0.27417610 - b * 0.32180189,
+         r * 0.21147017 - g * 0.52261711 + b * 0.31114694)
+    };
+
+    let (lhs, rhs) = (yiq(lhs), yiq(rhs));
+    let (dy, di, dq) = (lhs.0 - rhs.0, lhs.1 - rhs.1, lhs.2 - rhs.2);
+    0.5053 * dy * dy + 0.299 * di * di + 0.1957 * dq * dq
+}
+
+/// Compares two RGBA images of the same size, returns how many pixels were
+/// too different along w/ a diff image highlighting them.
+fn compare(expected: &[u8], actual: &[u8]) -> (usize, Vec<u8>) {
+    assert_eq!(expected.len(), actual.len());
+
+    let max_delta = MAX_DELTA * PIXEL_THRESHOLD * PIXEL_THRESHOLD;
+    let mut mismatched = 0;
+    let mut diff = Vec::with_capacity(expected.len());
+    for (lhs, rhs) in expected.chunks(4).zip(actual.chunks(4)) {
+        if perceptual_delta(lhs, rhs) > max_delta {
+            mismatched += 1;
+            diff.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            let luma = (lhs[0] as f64 * 0.299 + lhs[1] as f64 * 0.587 + lhs[2] as f64 * 0.114) as u8;
+            let faded = 255 - (255 - luma) / 4;
+            diff.extend_from_slice(&[faded, faded, faded, 255]);
+        }
+    }
+
+    (mismatched, diff)
+}
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden")
+}
+
+fn output_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target").join("golden")
+}
+
+fn new_renderer() -> SoftRenderer {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("simple-font.tga");
+    SoftRenderer::new(font::load_font(&path).expect("could not load the default font"))
+}
+
+/// Checks the last frame drawn by `renderer` against the reference image for `name`
+fn assert_golden(name: &str, renderer: &SoftRenderer) {
+    let (width, height) = (renderer.dim().0 as usize, renderer.dim().1 as usize);
+    let actual   = renderer.pixels();
+    let expected = golden_dir().join(format!("{}.tga", name));
+
+    if env::var_os("KOKO_BLESS").is_some() {
+        util::save_image_tga_rle(&expected, &actual, (width, height))
+            .expect("could not write reference image");
+        return;
+    }
+
+    let out_dir = output_dir();
+    fs::create_dir_all(&out_dir).expect("could not create golden output dir");
+    let actual_path = out_dir.join(format!("{}.actual.tga", name));
+    let diff_path   = out_dir.join(format!("{}.diff.tga", name));
+    let _ = fs::remove_file(&actual_path);
+    let _ = fs::remove_file(&diff_path);
+
+    let (reference, dim) = match util::load_image_tga(&expected) {
+        Ok(reference) => reference,
+        Err(err) => {
+            util::save_image_tga(&actual_path, &actual, (width, height)).expect("could not write actual image");
+            panic!("{}: no reference image ({}), bless it w/ `KOKO_BLESS=1 cargo test golden` \
+                    after checking {:?}", name, err, actual_path);
+        },
+    };
+
+    if dim != (width, height) {
+        util::save_image_tga(&actual_path, &actual, (width, height)).expect("could not write actual image");
+        panic!("{}: drew a {:?} frame, but the reference is {:?}", name, (width, height), dim);
+    }
+
+    let (mismatched, diff) = compare(&reference, &actual);
+    if mismatched as f64 > MAX_MISMATCH * (width * height) as f64 {
+        util::save_image_tga(&actual_path, &actual, (width, height)).expect("could not write actual image");
+        util::save_image_tga(&diff_path, &diff, (width, height)).expect("could not write diff image");
+        panic!("{}: {} of {} pixels differ from the reference, see {:?}",
+               name, mismatched, width * height, diff_path);
+    }
+}
+
+/// Replays `tests/golden/<name>.rec` & checks the frame koko would draw after it
+fn assert_golden_replay(name: &str) {
+    let path = golden_dir().join(format!("{}.rec", name));
+    let recording = Recording::load(&path)
+        .unwrap_or_else(|err| panic!("could not load {:?}: {}", path, err));
+
+    let mut canvas = Canvas::new(Bindings::defaults());
+    record::replay(&recording, &mut canvas);
+
+    let mut renderer = new_renderer();
+    renderer.begin_frame(canvas.window_dim, COLOR_CLEAR);
+    render::draw_canvas(&mut renderer, &canvas);
+    render::draw_hud(&mut renderer, &canvas, None);
+    renderer.end_frame();
+
+    assert_golden(name, &renderer);
+}
+
+#[test]
+fn brushes() { assert_golden_replay("brushes"); }
+
+#[test]
+fn zoom() { assert_golden_replay("zoom"); }
+
+#[test]
+fn pan() { assert_golden_replay("pan"); }
+
+#[test]
+fn minimap() { assert_golden_replay("minimap"); }
+
+#[test]
+fn bookmarks() { assert_golden_replay("bookmarks"); }
+
+#[test]
+fn help() { assert_golden_replay("help"); }
+
+#[test]
+fn text() {
+    let mut renderer = new_renderer();
+    renderer.begin_frame((320, 180), COLOR_CLEAR);
+
+    let spans = [
+        Span { text: "koko ",     color: [1.0, 1.0, 0.0, 1.0] },
+        Span { text: "golden ",   color: [0.0, 1.0, 1.0, 1.0] },
+        Span { text: "text",      color: [1.0, 0.0, 1.0, 1.0] },
+    ];
+
+    let style  = TextStyle { size: 16.0, align: Align::Left, max_width: None };
+    let layout = text::layout_spans(renderer.font(), &spans, &style);
+    renderer.draw_text(&layout, (8.0, 8.0), &TextPaint::new([1.0; 4]));
+
+    let style  = TextStyle { size: 12.0, align: Align::Center, max_width: Some(160.0) };
+    let layout = text::layout(renderer.font(), "wrapped and centered, with a shadow and a panel behind it", &style);
+    let paint  = TextPaint {
+        color:  [1.0; 4],
+        shadow: Some([0.0, 0.0, 0.0, 1.0]),
+        panel:  Some([0.2, 0.4, 0.8, 0.6]),
+    };
+
+    let pos = layout.anchored(Anchor::Center, renderer.dim(), 0.0);
+    renderer.draw_text(&layout, pos, &paint);
+
+    let style  = TextStyle { size: 8.0, align: Align::Right, max_width: None };
+    let layout = text::layout(renderer.font(), "small\nright aligned", &style);
+    let pos    = layout.anchored(Anchor::BottomRight, renderer.dim(), 4.0);
+    renderer.draw_text(&layout, pos, &TextPaint::new([0.5, 1.0, 0.5, 1.0]));
+
+    renderer.end_frame();
+    assert_golden("text", &renderer);
+}
+
+#[test]
+fn zoomed_out_detail_looks_the_same() {
+    // a dense spiral, sampled every pixel & a half w/ varying pressure
+    let mut points = vec![];
+    let mut t = 0.0f64;
+    while t < 40.0 {
+        let radius = 20.0 + t * 12.0;
+        points.push(ControlPoint {
+            screen_xy: V2(640 + (radius * t.cos()) as i64, 360 + (radius * t.sin()) as i64),
+            pressure:  0.5 + 0.5 * (t * 3.0).sin() as f32,
+            tilt:      None,
+        });
+
+        t += 1.5 / radius;
+    }
+
+    let strokes = [Stroke::new((1280, 720), 1.0, 0.0, V2(0, 0), points)];
+    let stroke  = &strokes[0];
+
+    // the same stroke, w/ every level of detail left at full detail
+    let mut full_detail = strokes.clone();
+    for lod in &mut full_detail[0].lods { *lod = stroke.samples.clone(); }
+    for &zoom in &[0.3, 0.12] {
+        let view = View { dim: (1280, 720), scale: zoom, scanbox: V2(0, 0), rotation: 0.0 };
+        let lod  = lod::level_for(view.scale, stroke.scale);
+        assert!(lod > 0 && stroke.lod(lod).len() < stroke.samples.len() * 3 / 4, "lod {} at {}", lod, zoom);
+
+        let mut simplified = new_renderer();
+        simplified.begin_frame(view.dim, COLOR_CLEAR);
+        simplified.draw_strokes(&strokes, &view);
+
+        let mut full = new_renderer();
+        full.begin_frame(view.dim, COLOR_CLEAR);
+        full.draw_strokes(&full_detail, &view);
+
+        let (mismatched, _) = compare(&full.pixels(), &simplified.pixels());
+        assert!(mismatched as f64 <= MAX_MISMATCH * (1280 * 720) as f64, "{} pixels differ at {}", mismatched, zoom);
+    }
+}
+
+#[test]
+fn backgrounds() {
+    // panned, zoomed & spun, so the grids have to follow along
+    let view = View { dim: (320, 180), scale: 1.6, scanbox: V2(37, -21), rotation: 0.3 };
+    for &background in &[Background::Dots, Background::Grid, Background::Isometric] {
+        let mut renderer = new_renderer();
+        renderer.begin_frame(view.dim, COLOR_CLEAR);
+        renderer.draw_background(background, &view);
+        renderer.end_frame();
+
+        assert_golden(&format!("background_{}", background.name()), &renderer);
+    }
+}
+
+#[test]
+fn compare_tolerates_small_differences() {
+    let expected = vec![10, 20, 30, 255, 200, 100, 50, 255];
+    let actual   = vec![12, 21, 29, 255, 200, 100, 50, 255];
+    assert_eq!(compare(&expected, &actual).0, 0);
+
+    let actual = vec![10, 20, 30, 255, 20, 100, 200, 255];
+    let (mismatched, diff) = compare(&expected, &actual);
+    assert_eq!(mismatched, 1);
+    assert_eq!(&diff[4..], &[255, 0, 0, 255]);
+}
+
+#[test]
+fn rle_images_round_trip() {
+    let mut rgba = vec![];
+    for idx in 0..300u32 {
+        // long runs, short runs & noise, across rows
+        let px = if idx < 140 || idx % 7 == 0 { [1, 2, 3, 4] } else { [idx as u8, 0, 255, 128] };
+        rgba.extend_from_slice(&px);
+    }
+
+    let out_dir = output_dir();
+    fs::create_dir_all(&out_dir).expect("could not create golden output dir");
+    let path = out_dir.join("rle_round_trip.tga");
+    util::save_image_tga_rle(&path, &rgba, (20, 15)).expect("could not write image");
+
+    let (loaded, dim) = util::load_image_tga(&path).expect("could not read image");
+    assert_eq!(dim, (20, 15));
+    assert_eq!(loaded, rgba);
+}
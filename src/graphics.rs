@@ -1,25 +1,232 @@
 //use glium::backend::glutin_backend::GlutinFacade;
-use glium::draw_parameters::DrawParameters;
-use glium::{self, backend::Facade, texture, Surface};
-use glium::uniforms::{MinifySamplerFilter, MagnifySamplerFilter, SamplerWrapFunction};
+use glium::draw_parameters::{Blend, BlendingFunction, DrawParameters, LinearBlendingFactor};
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::{self, backend::Facade, texture, Display, Rect, Surface, VertexBuffer};
+use glium::uniforms::{MinifySamplerFilter, MagnifySamplerFilter, SamplerWrapFunction, Uniforms};
+use rusttype::gpu_cache;
+use std::collections::HashSet;
+use std::rc::Rc;
 
-use util;
+use background::{self, Background};
+use canvas::{BlendMode, Stroke};
+use font::Font;
+use lod;
+use render::{self, Renderer, View, COLOR_BG, COLOR_GRID_MAJOR, COLOR_GRID_MINOR};
+use tiles::{self, TileCache, TileKey, TILE_PX, MAX_TILES};
+use text::{self, TextLayout, TextPaint, TextQuad};
 
-static TEXT_VRT: &'static str = include_str!("shaders/text.v.glsl");
-static TEXT_FRG: &'static str = include_str!("shaders/text.f.glsl");
+static FLAT_VRT:  &str = include_str!("shaders/flat.v.glsl");
+static FLAT_FRG:  &str = include_str!("shaders/basic.f.glsl");
+static DAB_VRT:   &str = include_str!("shaders/dab.v.glsl");
+static DAB_FRG:   &str = include_str!("shaders/dab.f.glsl");
+static TEXT_VRT: &str = include_str!("shaders/text.v.glsl");
+static TEXT_FRG: &str = include_str!("shaders/text.f.glsl");
+static TILE_VRT: &str = include_str!("shaders/tile.v.glsl");
+static TILE_FRG: &str = include_str!("shaders/tile.f.glsl");
+static ATLAS_VRT: &str = include_str!("shaders/atlas.v.glsl");
+static ATLAS_FRG: &str = include_str!("shaders/atlas.f.glsl");
+static BACKGROUND_FRG: &str = include_str!("shaders/background.f.glsl");
+
+static TEXT_BATCH_VERTS: usize = 6 * 256;
+static OVERLAY_VERTS:    usize = 6 * 256;
+
+/// More tiles than this on screen and it's cheaper to just draw the strokes
+static MAX_VISIBLE_TILES: usize = 256;
+
+/// Tiles drawn into each page of the atlas, see `TileAtlas`
+static TILES_PER_PAGE: u32 = 64;
 
 #[derive(Copy, Clone, Debug)]
 pub struct Vert2 {
     pub pos:   [f32; 3],
-    pub color: [f32; 3],
+
+    /// RGBA, w/o the alpha premultiplied
+    pub color: [f32; 4],
 }
 
 implement_vertex!(Vert2, pos, color);
 
+/// A corner of a glyph quad, positioned in pixels from the upper left of the window
+#[derive(Copy, Clone, Debug)]
+pub struct TextVert {
+    pub pos:   [f32; 2],
+    pub uv:    [f32; 2],
+    pub color: [f32; 4],
+}
+
+implement_vertex!(TextVert, pos, uv, color);
+
+/// A corner of the quad every dab is drawn with, see `render::DAB_CORNERS`
+#[derive(Copy, Clone, Debug)]
+pub struct DabCorner {
+    pub corner: [f32; 2],
+}
+
+implement_vertex!(DabCorner, corner);
+
+/// Where a dab is & how big it is, one per instance
+#[derive(Copy, Clone, Debug)]
+pub struct DabShape {
+    pub center: [f32; 2],
+    pub radius: [f32; 2],
+}
+
+implement_vertex!(DabShape, center, radius);
+
+#[derive(Copy, Clone, Debug)]
+pub struct DabColor {
+    pub color: [f32; 4],
+}
+
+implement_vertex!(DabColor, color);
+
+/// A corner of a layer stretched over the screen (or a tile)
+#[derive(Copy, Clone, Debug)]
+pub struct TileVert {
+    pub pos: [f32; 2],
+    pub uv:  [f32; 2],
+}
+
+implement_vertex!(TileVert, pos, uv);
+
+/// A corner of a cached tile already moved onto the screen, w/ the layer of
+/// the atlas page it was drawn into.
+#[derive(Copy, Clone, Debug)]
+pub struct AtlasVert {
+    pub pos:   [f32; 2],
+    pub uv:    [f32; 2],
+    pub layer: f32,
+}
+
+implement_vertex!(AtlasVert, pos, uv, layer);
+
+/// Where in the atlas a tile was drawn, see `TileAtlas`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+struct TileSlot {
+    page:  usize,
+    layer: u32,
+}
+
+/// Cached tiles are drawn into the layers of a few big texture arrays (pages)
+/// instead of a texture each, so every tile on a page is drawn w/ one call.
+/// Pages are added as they're needed, & slots are reused once the tile
+/// cache has forgotten about them.
+struct TileAtlas {
+    pages: Vec<texture::Texture2dArray>,
+}
+
+impl TileAtlas {
+    fn new() -> TileAtlas {
+        TileAtlas { pages: vec![] }
+    }
+
+    /// A slot not in `taken`, which is then marked as taken
+    fn alloc<F: Facade>(&mut self, context: &F, taken: &mut HashSet<TileSlot>) -> TileSlot {
+        let free = (0..self.pages.len())
+            .flat_map(|page| (0..TILES_PER_PAGE).map(move |layer| TileSlot { page, layer }))
+            .find(|slot| !taken.contains(slot));
+
+        let slot = match free {
+            Some(slot) => slot,
+            None => {
+                let page = texture::Texture2dArray::empty_with_format(context,
+                    texture::UncompressedFloatFormat::U8U8U8U8, texture::MipmapsOption::NoMipmap,
+                    TILE_PX, TILE_PX, TILES_PER_PAGE)
+                    .expect("could not alloc tile atlas");
+
+                self.pages.push(page);
+                TileSlot { page: self.pages.len() - 1, layer: 0 }
+            },
+        };
+
+        taken.insert(slot);
+        slot
+    }
+}
+
+/// Dynamic atlas of rasterized glyphs for an outline font.
+///
+/// Glyphs are cached per size, so text drawn at several sizes (or zoom levels)
+/// shares a single texture. The atlas grows when a frame's text won't fit.
+struct GlyphCache {
+    cache:   gpu_cache::Cache<'static>,
+    texture: texture::Texture2d,
+
+    // so running out of room is only complained about once
+    is_full: bool,
+}
+
+static GLYPH_CACHE_DIM: u32 = 512;
+static GLYPH_CACHE_MAX: u32 = 4096;
+
+impl GlyphCache {
+    fn new<F: Facade>(context: &F, dim: u32) -> GlyphCache {
+        let cache = gpu_cache::Cache::builder()
+            .dimensions(dim, dim)
+            .build();
+
+        let texture = texture::Texture2d::empty(context, dim, dim)
+            .expect("could not alloc glyph cache");
+
+        GlyphCache { cache, texture, is_full: false }
+    }
+
+    /// Rasterizes any queued glyphs which are not already in the atlas, the
+    /// atlas is doubled in size (and the queue retried) if they don't all fit.
+    /// Once it's as big as it gets, glyphs which still don't fit are left out.
+    fn upload_queued<F: Facade>(&mut self, context: &F) -> Result<(), gpu_cache::CacheWriteErr> {
+        loop {
+            let texture = &self.texture;
+            let result  = self.cache.cache_queued(|rect, coverage| {
+                // expand to white pixels w/ coverage in alpha, same as bitmap atlases
+                let rgba = coverage.iter()
+                    .flat_map(|&alpha| [0xFF, 0xFF, 0xFF, alpha])
+                    .collect::<Vec<u8>>();
+
+                texture.main_level().write(glium::Rect {
+                    left:   rect.min.x,
+                    bottom: rect.min.y,
+                    width:  rect.width(),
+                    height: rect.height(),
+                }, texture::RawImage2d::from_raw_rgba(rgba, (rect.width(), rect.height())));
+            });
+
+            match result {
+                Ok(_) => return Ok(()),
+                Err(err) => {
+                    let (dim, _) = self.cache.dimensions();
+                    if dim >= GLYPH_CACHE_MAX { return Err(err) }
+
+                    println!("glyph cache is full, growing to {}px", dim * 2);
+                    self.cache.to_builder()
+                        .dimensions(dim * 2, dim * 2)
+                        .rebuild(&mut self.cache);
+
+                    self.texture = texture::Texture2d::empty(context, dim * 2, dim * 2)
+                        .expect("could not alloc glyph cache");
+                },
+            }
+        }
+    }
+}
+
+/// The GPU side of whichever font the blitter was created with.
+enum Atlas {
+    Bitmap(texture::Texture2d),
+    Outline(GlyphCache),
+}
+
 /// On GPU Text Blitting program
+///
+/// Text is queued up into a batch of glyph quads which are uploaded to
+/// a single dynamic vertex buffer & drawn in one call by `flush()`.
 pub struct TextBlitter {
-    atlas_array: texture::texture2d_array::Texture2dArray,
-    vbuf:    glium::VertexBuffer<Vert2>,
+    context: Rc<glium::backend::Context>,
+    atlas:   Atlas,
+    font:    Font,
+    pending: Vec<TextQuad>,
+    verts:   Vec<TextVert>,
+    vbuf:    glium::VertexBuffer<TextVert>,
     program: glium::Program,
     indices: glium::index::NoIndices,
 }
@@ -29,25 +236,13 @@ impl TextBlitter {
     /// into GPU memory.
     ///
     /// This then returns a text-blitting helper which can be used to quickly draw
-    /// strings of ASCII characters to the screen.
+    /// strings of characters to the screen.
     ///
-    /// NOTE: currently only prints a subset of ASCII
-    /// NOTE: requires `simple-font.tga` in working directory
-    /// NOTE: will totally explode if you swap out other fonts
-    pub fn new<F: Facade>(context: &mut F) -> Self {
-        // simple square
+    /// NOTE: characters missing from a bitmap font are drawn as `?` (or skipped
+    ///       entirely if the font doesn't have one either.)
+    pub fn new<F: Facade>(context: &F, font: Font) -> Self {
         let indices = glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList);
-        let shape = [
-            Vert2 { pos: [-  1.0,  1.0,  0.0], color: [1.0, 1.0, 1.0] },
-            Vert2 { pos: [-0.875,  1.0,  0.0], color: [1.0, 1.0, 1.0] },
-            Vert2 { pos: [-  1.0, -1.0,  0.0], color: [1.0, 1.0, 1.0] },
- 
-            Vert2 { pos: [-  1.0, -1.0,  0.0], color: [1.0, 1.0, 1.0] },
-            Vert2 { pos: [-0.875, -1.0,  0.0], color: [1.0, 1.0, 1.0] },
-            Vert2 { pos: [-0.875,  1.0,  0.0], color: [1.0, 1.0, 1.0] },
-        ];
-
-        let vbuf = glium::VertexBuffer::new(context, &shape)
+        let vbuf = glium::VertexBuffer::empty_dynamic(context, TEXT_BATCH_VERTS)
             .expect("could not alloc vbuf");
 
         let program = match glium::Program::from_source(context, TEXT_VRT, TEXT_FRG, None) {
@@ -55,112 +250,728 @@ impl TextBlitter {
             Err(msg) => panic!("could not load shader: {}", msg),
         };
 
-        // TODO: read image dimension and dynamically size the atlas instead
-        //       of hardcoding it to the 8 page font sheet.
-        let (image, _dim) = util::load_image_tga("./simple-font.tga");
-        
-        let mut sprite_rows = vec![];
-        for i in (0..8).rev() {
-            let stride = 256 * 4 * 16;
-            let start  = i * stride;
-            let end    = start + stride;
+        // NOTE: atlases are uploaded top row first, so `v` grows downward
+        //       just like the pixel coordinates stored in each glyph.
+        let atlas = match font {
+            Font::Bitmap(ref font) => {
+                let image = texture::RawImage2d::from_raw_rgba(font.atlas.clone(), font.atlas_dim);
+                Atlas::Bitmap(texture::Texture2d::new(context, image)
+                    .expect("could not upload font atlas"))
+            },
 
-            println!("{} => {}", start,end);
+            Font::Outline(_) => Atlas::Outline(GlyphCache::new(context, GLYPH_CACHE_DIM)),
+        };
 
-            let row: Vec<u8> = (&image[start..end]).iter().cloned().collect();
-            let fuck = texture::RawImage2d::from_raw_rgba(row, (256,16));
-            sprite_rows.push(fuck);
+        TextBlitter {
+            context: context.get_context().clone(),
+            atlas,
+            font,
+            pending: vec![],
+            verts:   Vec::with_capacity(TEXT_BATCH_VERTS),
+            vbuf,
+            program,
+            indices,
         }
+    }
 
-        let atlas_array = texture::texture2d_array::Texture2dArray::new(context,sprite_rows)
-            .expect("could not uplaod texture array");
+    pub fn font(&self) -> &Font { &self.font }
 
-        TextBlitter {
-            atlas_array: atlas_array,
-            vbuf:    vbuf,
-            program: program,
-            indices: indices,
-        }
-    }
-
-    pub fn draw(&self, text: &str, font_size: f32, ofs: (f32, f32), target: &mut glium::Frame) {
-        let mapping: Vec<(f32,f32)> = text.chars()
-            .map(TextBlitter::ascii_to_ofs)
-            .collect();
-
-        // NOTE: there are two translation steps
-        //
-        // c_pos is applied before we scale the text so that it's computation
-        // can be done w/o taking current text scaling into account
-        //
-        // w_pos is applied after we scale the thing so we can just move
-        // it around as a single unit of text
-        //
-        // so essentially a character is:
-        //   1. translated so the upper left corner of the character is at the origin
-        //      (instead of the origin being the center of the sprite sheet)
-        //
-        //   2. translated over some number of characters (character offset * (16.0 / 128.0))
-        //   3. scaled to the user's preferred text size
-        //   4. translated to where the user wanted it on the screen (by upper left corner)
-        //
-
-
-        let mut ofs_x = 0.0;
-        for &(char_x, char_y) in &mapping {
-            let char_uni = uniform! {
-                atlas_array: self.atlas_array.sampled()
-                    .minify_filter(MinifySamplerFilter::Nearest)
-                    .magnify_filter(MagnifySamplerFilter::Nearest)
-                    .wrap_function(SamplerWrapFunction::Clamp),
+    /// Adds a block of text to the batch, nothing is drawn until `flush()`
+    pub fn queue_layout(&mut self, layout: &TextLayout, ofs: (f32, f32), paint: &TextPaint) {
+        let quads = text::place_layout(&self.font, layout, ofs, paint);
+        self.pending.extend(quads);
+    }
+
+    /// Draws every queued glyph in a single draw call & empties the batch.
+    pub fn flush(&mut self, target: &mut glium::Frame) {
+        if self.pending.is_empty() { return }
+
+        // outline glyphs all need to be resident in the cache at the same time,
+        // so they're only cached once the whole batch is known.
+        if let Atlas::Outline(ref mut cache) = self.atlas {
+            for pending in &self.pending {
+                if let TextQuad::Outline(ref glyph, _) = *pending {
+                    cache.cache.queue_glyph(0, glyph.clone());
+                }
+            }
+
+            // NOTE: glyphs which didn't make it into the cache are skipped below
+            if let Err(err) = cache.upload_queued(&self.context) {
+                if !cache.is_full {
+                    println!("glyph cache is full, some text won't be drawn: {}", err);
+                    cache.is_full = true;
+                }
+            }
+        }
+
+        self.verts.clear();
+        for pending in self.pending.drain(..) {
+            let (rect, uv, color) = match pending {
+                // NOTE: negative texture coordinates tell the shader not to sample the atlas
+                TextQuad::Panel(rect, color) => (rect, [-1.0, -1.0, 0.0, 0.0], color),
+
+                TextQuad::Bitmap(rect, atlas_rect, color) => {
+                    let (atlas_w, atlas_h) = match self.font {
+                        Font::Bitmap(ref font) => (font.atlas_dim.0 as f32, font.atlas_dim.1 as f32),
+                        Font::Outline(_) => unreachable!("bitmap glyph queued for an outline font"),
+                    };
+
+                    (rect, [atlas_rect[0] as f32 / atlas_w, atlas_rect[1] as f32 / atlas_h,
+                            atlas_rect[2] as f32 / atlas_w, atlas_rect[3] as f32 / atlas_h], color)
+                },
 
-                c_pos: [ofs_x, 0.0, 0.0f32],
-                c_ofs: [char_x, char_y],
-                w_ofs: [ofs.0, ofs.1, 0.0f32],
-                scale: font_size,
+                TextQuad::Outline(glyph, color) => {
+                    let cache = match self.atlas {
+                        Atlas::Outline(ref cache) => cache,
+                        Atlas::Bitmap(_) => unreachable!("outline glyph queued for a bitmap font"),
+                    };
+
+                    let (uv, px) = match cache.cache.rect_for(0, &glyph) {
+                        Ok(Some(coords)) => coords,
+                        _ => continue,
+                    };
+
+                    ([px.min.x as f32, px.min.y as f32, px.width() as f32, px.height() as f32],
+                     [uv.min.x, uv.min.y, uv.width(), uv.height()],
+                     color)
+                },
             };
 
-            ofs_x += 16.0 / 128.0; // move forward one character in textspace
+            let (x0, y0, x1, y1) = (rect[0], rect[1], rect[0] + rect[2], rect[1] + rect[3]);
+            let (u0, v0, u1, v1) = (uv[0], uv[1], uv[0] + uv[2], uv[1] + uv[3]);
+            self.verts.extend_from_slice(&[
+                TextVert { pos: [x0, y0], uv: [u0, v0], color },
+                TextVert { pos: [x1, y0], uv: [u1, v0], color },
+                TextVert { pos: [x0, y1], uv: [u0, v1], color },
+
+                TextVert { pos: [x0, y1], uv: [u0, v1], color },
+                TextVert { pos: [x1, y1], uv: [u1, v1], color },
+                TextVert { pos: [x1, y0], uv: [u1, v0], color },
+            ]);
+        }
+
+        if self.verts.is_empty() { return }
+
+        // grow the batch buffer if this frame has more text than any before it
+        if self.verts.len() > self.vbuf.len() {
+            self.vbuf = glium::VertexBuffer::empty_dynamic(&self.context, self.verts.len().next_power_of_two())
+                .expect("could not alloc vbuf");
+        }
+
+        let batch = self.vbuf.slice(0..self.verts.len())
+            .expect("text batch out of range");
+
+        batch.write(&self.verts);
+
+        let (atlas, filter) = match self.atlas {
+            Atlas::Bitmap(ref texture)   => (texture, MagnifySamplerFilter::Nearest),
+            Atlas::Outline(ref cache)    => (&cache.texture, MagnifySamplerFilter::Linear),
+        };
+
+        let (win_w, win_h) = target.get_dimensions();
+        let text_uni = uniform! {
+            atlas: atlas.sampled()
+                .minify_filter(MinifySamplerFilter::Nearest)
+                .magnify_filter(filter)
+                .wrap_function(SamplerWrapFunction::Clamp),
+
+            dim: [win_w as f32, win_h as f32],
+        };
+
+        target.draw(batch, self.indices, &self.program, &text_uni, &DrawParameters {
+            blend: Blend::alpha_blending(),
+            .. Default::default()
+        }).expect("could not blit text");
+    }
+}
+
+/// How dabs are drawn onto a stroke's layer: where they overlap the more
+/// opaque one wins. Every dab of a stroke is the same color & they're
+/// premultiplied (see `dab.f.glsl`) so taking the max of each channel does it.
+///
+/// NOTE: layers (& tiles) aren't multisampled, so the only smoothing strokes
+///       get is from the shader, see `antialias` in `dab.f.glsl`.
+fn dab_params() -> DrawParameters<'static> {
+    DrawParameters {
+        blend: Blend {
+            color: BlendingFunction::Max,
+            alpha: BlendingFunction::Max,
+            constant_value: (0.0, 0.0, 0.0, 0.0),
+        },
+        .. Default::default()
+    }
+}
+
+/// Blends w/ `src * source + dst * destination`, for color & alpha separately
+fn blend_with(color: (LinearBlendingFactor, LinearBlendingFactor), alpha: (LinearBlendingFactor, LinearBlendingFactor)) -> Blend {
+    Blend {
+        color: BlendingFunction::Addition { source: color.0, destination: color.1 },
+        alpha: BlendingFunction::Addition { source: alpha.0, destination: alpha.1 },
+        constant_value: (0.0, 0.0, 0.0, 0.0),
+    }
+}
+
+/// Draws w/ these to blend a (premultiplied) layer onto what's below it,
+/// see `render::composite`.
+///
+/// NOTE: GL can't add two blending factors together, so multiplying takes
+///       two passes: `src * dst + dst * (1 - src.a)` leaving alpha alone,
+///       then `+ src * (1 - dst.a)` w/ the alpha dst had all along.
+fn composite_blends(blend: BlendMode) -> Vec<Blend> {
+    use self::LinearBlendingFactor::*;
+
+    match blend {
+        BlendMode::Normal => vec![blend_with((One, OneMinusSourceAlpha), (One, OneMinusSourceAlpha))],
+        BlendMode::Multiply => vec![
+            blend_with((DestinationColor, OneMinusSourceAlpha), (Zero, One)),
+            blend_with((OneMinusDestinationAlpha, One), (OneMinusDestinationAlpha, One)),
+        ],
+        BlendMode::Screen => vec![blend_with((One, OneMinusSourceColor), (One, OneMinusSourceAlpha))],
+        BlendMode::Add    => vec![blend_with((One, One), (One, One))],
+        BlendMode::Erase  => vec![blend_with((Zero, OneMinusSourceAlpha), (Zero, OneMinusSourceAlpha))],
+    }
+}
+
+/// The dabs of a stroke at one level of detail, as per-instance attributes.
+///
+/// NOTE: colors are kept in a buffer of their own so recoloring a stroke
+///       doesn't mean uploading its shape all over again.
+struct DabBuffers {
+    shapes: VertexBuffer<DabShape>,
+    colors: VertexBuffer<DabColor>,
+}
+
+impl DabBuffers {
+    /// The vertex sources to draw every dab w/ a copy of the `corners` quad
+    fn instanced<'a>(&'a self, corners: &'a VertexBuffer<DabCorner>)
+        -> (&'a VertexBuffer<DabCorner>, glium::vertex::PerInstance<'a>, glium::vertex::PerInstance<'a>) {
+        (corners,
+         self.shapes.per_instance().expect("instanced rendering is not supported"),
+         self.colors.per_instance().expect("instanced rendering is not supported"))
+    }
+}
+
+/// GPU side of a `canvas::Stroke`, w/ the dabs for each of its levels of
+/// detail that's been drawn so far.
+struct ControlPath {
+    dim:     (u32, u32),
+    color:   (u8, u8, u8),
+    buffers: Vec<Option<DabBuffers>>,
+}
+
+impl ControlPath {
+    pub fn new() -> ControlPath {
+        ControlPath {
+            dim:     (0, 0),
+            color:   render::COLOR_PEN,
+            buffers: vec![],
+        }
+    }
+
+    /// Catches up w/ the stroke's color by rewriting just the dabs' colors,
+    /// responds true if anything which was already uploaded changed.
+    pub fn recolor(&mut self, stroke: &Stroke) -> bool {
+        if stroke.color == self.color { return false }
+        self.color = stroke.color;
+
+        let mut changed = false;
+        for (lod, buffers) in self.buffers.iter().enumerate() {
+            if let Some(ref buffers) = *buffers {
+                let colors = render::stroke_dabs(stroke, lod, self.dim).iter()
+                    .map(|dab| DabColor { color: dab.color })
+                    .collect::<Vec<_>>();
+
+                buffers.colors.write(&colors);
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
+    // cleans up shop and prepares buffer for a draw call
+    pub fn draw(&mut self, context: &Display, dim: (u32, u32), stroke: &Stroke, lod: usize) -> &DabBuffers {
+        // NOTE: paths are stretched w/ the window, so they're redone if it changes
+        if dim != self.dim {
+            self.dim = dim;
+            self.buffers.clear();
+        }
+
+        if self.buffers.len() <= lod { self.buffers.resize_with(lod + 1, || None); }
+
+        if self.buffers[lod].is_none() {
+            let dabs   = render::stroke_dabs(stroke, lod, dim);
+            let shapes = dabs.iter().map(|dab| DabShape { center: dab.center, radius: dab.radius }).collect::<Vec<_>>();
+            let colors = dabs.iter().map(|dab| DabColor { color: dab.color }).collect::<Vec<_>>();
+
+            self.color = stroke.color;
+            self.buffers[lod] = Some(DabBuffers {
+                shapes: glium::VertexBuffer::immutable(context, &shapes).expect("could not alloc vbuf"),
+                colors: glium::VertexBuffer::dynamic(context, &colors).expect("could not alloc vbuf"),
+            });
+        }
+
+        self.buffers[lod].as_ref().expect("path was just filled in")
+    }
+}
+
+/// The pixels of a `dim` sized target which a stroke reaching over `bounds`
+/// (in stroke space) could touch, once `project`ed onto the unit square.
+/// `None` if it misses the target entirely.
+///
+/// NOTE: it's grown a couple pixels past the dabs, for their antialiased
+///       fringe (see `dab.v.glsl`) & rounding.
+fn scissor_for<F: Fn([f32; 2]) -> [f32; 2]>(bounds: [f32; 4], dim: (u32, u32), project: F) -> Option<Rect> {
+    let corners = [[bounds[0], bounds[1]], [bounds[2], bounds[1]], [bounds[0], bounds[3]], [bounds[2], bounds[3]]];
+    let px = corners.iter()
+        .map(|&corner| project(corner))
+        .map(|unit| ((unit[0] + 1.0) / 2.0 * dim.0 as f32, (unit[1] + 1.0) / 2.0 * dim.1 as f32))
+        .fold([f32::MAX, f32::MAX, f32::MIN, f32::MIN], |acc, (x, y)| [acc[0].min(x), acc[1].min(y), acc[2].max(x), acc[3].max(y)]);
+
+    let (left, bottom) = ((px[0].floor() - 2.0).max(0.0), (px[1].floor() - 2.0).max(0.0));
+    let (right, top)   = ((px[2].ceil() + 2.0).min(dim.0 as f32), (px[3].ceil() + 2.0).min(dim.1 as f32));
+    if right <= left || top <= bottom { return None }
+
+    Some(Rect { left: left as u32, bottom: bottom as u32, width: (right - left) as u32, height: (top - bottom) as u32 })
+}
 
-            target.draw(&self.vbuf, &self.indices, &self.program, &char_uni, &DrawParameters {
+fn empty_layer<F: Facade>(context: &F, dim: (u32, u32)) -> texture::Texture2d {
+    texture::Texture2d::empty_with_format(context,
+        texture::UncompressedFloatFormat::U8U8U8U8, texture::MipmapsOption::NoMipmap, dim.0, dim.1)
+        .expect("could not alloc layer")
+}
+
+/// Draws strokes one layer at a time, see `Renderer::draw_strokes`
+struct LayerPipeline {
+    dab_corners:  VertexBuffer<DabCorner>,
+    dab_program:  glium::Program,
+    tile_program: glium::Program,
+    layer_quad:   VertexBuffer<TileVert>,
+    indices:      glium::index::NoIndices,
+}
+
+impl LayerPipeline {
+    /// Stretches a (premultiplied) `texture` over all of `target`, or just
+    /// the `scissor`ed part of it.
+    fn blit<S: Surface>(&self, target: &mut S, texture: &texture::Texture2d, blend: Blend, scissor: Option<Rect>) {
+        let layer_uni = uniform! {
+            tile: texture.sampled()
+                .minify_filter(MinifySamplerFilter::Nearest)
+                .magnify_filter(MagnifySamplerFilter::Nearest)
+                .wrap_function(SamplerWrapFunction::Clamp),
+        };
+
+        target.draw(&self.layer_quad, self.indices, &self.tile_program, &layer_uni, &DrawParameters {
+            blend,
+            scissor,
+            .. Default::default()
+        }).expect("could not blit layer");
+    }
+
+    /// Draws the dabs of each stroke onto `layer` (which is as big as
+    /// `target`) w/ `dab_uni`, then blends that onto `target`.
+    ///
+    /// NOTE: only the part of the layer a stroke lands on (see `scissor_for`)
+    ///       is cleared, drawn & blended, so zoomed out strokes only cost
+    ///       as much fill as they cover rather than the whole target.
+    fn draw<S: Surface, U: Uniforms>(&self, context: &Display, target: &mut S, layer: &texture::Texture2d,
+                                     strokes: &[(&DabBuffers, BlendMode, Rect)], dab_uni: &U) {
+        for &(dabs, blend, scissor) in strokes {
+            {
+                let mut scratch = SimpleFrameBuffer::new(context, layer)
+                    .expect("could not draw to layer");
+
+                let params = DrawParameters { scissor: Some(scissor), .. dab_params() };
+                scratch.clear(Some(&scissor), Some((0.0, 0.0, 0.0, 0.0)), false, None, None);
+                scratch.draw(dabs.instanced(&self.dab_corners), self.indices, &self.dab_program, dab_uni, &params)
+                    .expect("could not blit stroke to layer");
+            }
+
+            for params in composite_blends(blend) {
+                self.blit(target, layer, params, Some(scissor));
+            }
+        }
+    }
+}
+
+/// The layers strokes are drawn through when they're drawn straight to the
+/// screen, instead of into tiles.
+struct ScreenLayers {
+    dim:   (u32, u32),
+    ink:   texture::Texture2d,
+    layer: texture::Texture2d,
+}
+
+/// Draws koko w/ OpenGL, into the window's back buffer.
+///
+/// Each stroke's dabs are uploaded once (per level of detail) as instances
+/// of a single quad, which the vertex shader moves & sizes into place. Strokes are drawn into cached tiles (see
+/// `tiles`) which are then stretched onto the screen, unless the view is
+/// zoomed out so far that there'd be too many tiles to bother.
+pub struct GlRenderer {
+    context: Display,
+    target:  Option<glium::Frame>,
+
+    paths:        Vec<ControlPath>,
+    layers:       LayerPipeline,
+    tile_layer:   texture::Texture2d,
+    screen:       Option<ScreenLayers>,
+    tiles:        TileCache<TileSlot>,
+    atlas:        TileAtlas,
+    atlas_program: glium::Program,
+    tile_quads:   Vec<AtlasVert>,
+    tile_vbuf:    VertexBuffer<AtlasVert>,
+    overlay:      VertexBuffer<Vert2>,
+    indices:      glium::index::NoIndices,
+    path_program: glium::Program,
+    background_program: glium::Program,
+    text_blitter: TextBlitter,
+
+    antialiasing: bool,
+}
+
+impl GlRenderer {
+    pub fn new(context: Display, font: Font) -> GlRenderer {
+        let indices = glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList);
+
+        let flat_shader = match glium::Program::from_source(&context, FLAT_VRT, FLAT_FRG, None) {
+            Ok(program) => program,
+            Err(msg) => panic!("could not load shader: {}", msg),
+        };
+
+        let dab_shader = match glium::Program::from_source(&context, DAB_VRT, DAB_FRG, None) {
+            Ok(program) => program,
+            Err(msg) => panic!("could not load shader: {}", msg),
+        };
+
+        let tile_shader = match glium::Program::from_source(&context, TILE_VRT, TILE_FRG, None) {
+            Ok(program) => program,
+            Err(msg) => panic!("could not load shader: {}", msg),
+        };
+
+        let atlas_shader = match glium::Program::from_source(&context, ATLAS_VRT, ATLAS_FRG, None) {
+            Ok(program) => program,
+            Err(msg) => panic!("could not load shader: {}", msg),
+        };
+
+        // NOTE: the background is drawn w/ a quad over the whole screen, same as a layer
+        let background_shader = match glium::Program::from_source(&context, TILE_VRT, BACKGROUND_FRG, None) {
+            Ok(program) => program,
+            Err(msg) => panic!("could not load shader: {}", msg),
+        };
+
+        let overlay = glium::VertexBuffer::empty_dynamic(&context, OVERLAY_VERTS)
+            .expect("could not alloc vbuf");
+
+        let corners = render::DAB_CORNERS.iter().map(|&corner| DabCorner { corner }).collect::<Vec<_>>();
+        let dab_corners = glium::VertexBuffer::immutable(&context, &corners)
+            .expect("could not alloc vbuf");
+
+        let tile_vbuf = glium::VertexBuffer::empty_dynamic(&context, MAX_VISIBLE_TILES * 6)
+            .expect("could not alloc vbuf");
+
+        let corner = |x: f32, y: f32| TileVert { pos: [x * 2.0 - 1.0, y * 2.0 - 1.0], uv: [x, y] };
+        let layer_quad = glium::VertexBuffer::immutable(&context, &[
+            corner(0.0, 0.0), corner(1.0, 0.0), corner(0.0, 1.0),
+            corner(0.0, 1.0), corner(1.0, 1.0), corner(1.0, 0.0),
+        ]).expect("could not alloc vbuf");
+
+        let tile_layer = empty_layer(&context, (TILE_PX, TILE_PX));
+
+        let text_blitter = TextBlitter::new(&context, font);
+
+        GlRenderer {
+            context,
+            target:  None,
+
+            paths:        vec![],
+            layers:       LayerPipeline {
+                dab_corners,
+                dab_program:  dab_shader,
+                tile_program: tile_shader,
+                layer_quad,
+                indices,
+            },
+            tile_layer,
+            screen:       None,
+            tiles:        TileCache::new(),
+            atlas:        TileAtlas::new(),
+            atlas_program: atlas_shader,
+            tile_quads:   Vec::with_capacity(MAX_VISIBLE_TILES * 6),
+            tile_vbuf,
+            overlay,
+            indices,
+            path_program: flat_shader,
+            background_program: background_shader,
+            text_blitter,
+
+            antialiasing: true,
+        }
+    }
+
+    /// Makes sure every stroke has a place to keep its vertex buffers
+    fn sync_paths(&mut self, strokes: &[Stroke]) {
+        if strokes.len() < self.paths.len() { self.paths.clear(); }
+
+        while self.paths.len() < strokes.len() {
+            self.paths.push(ControlPath::new());
+        }
+    }
+
+    /// Draws the strokes (by index) which touch a tile into its slot of the
+    /// atlas, w/ the detail they need at the tile's zoom level.
+    fn render_tile(&mut self, key: TileKey, slot: TileSlot, strokes_in: &[usize], strokes: &[Stroke], dim: (u32, u32)) {
+        let zoom = 2f32.powi(key.level);
+        for &idx in strokes_in {
+            let lod = lod::level_for(zoom, strokes[idx].scale);
+            self.paths[idx].draw(&self.context, dim, &strokes[idx], lod);
+        }
+
+        {
+            let layer = self.atlas.pages[slot.page].main_level().layer(slot.layer)
+                .expect("tile slot out of range");
+
+            let mut target = SimpleFrameBuffer::new(&self.context, layer)
+                .expect("could not draw to tile");
+
+            target.clear_color(0.0, 0.0, 0.0, 0.0);
+
+            let (scale, ofs) = key.projection(dim);
+            let tile_uni = uniform! {
+                dim:       [TILE_PX as f32, TILE_PX as f32],
+                ofs:       [ofs[0], ofs[1], 0.0f32],
+                scale:     scale,
+                rotation:  0.0f32,
+                antialias: self.antialiasing,
+            };
+
+            let tiles   = &self.tiles;
+            let layered = strokes_in.iter().filter_map(|&idx| {
+                let bounds  = tiles.stroke_bounds(idx).expect("tiles weren't synced w/ the strokes");
+                let scissor = scissor_for(bounds, (TILE_PX, TILE_PX), |pos| [pos[0] * scale[0] + ofs[0], pos[1] * scale[1] + ofs[1]])?;
+                let lod     = lod::level_for(zoom, strokes[idx].scale);
+                Some((self.paths[idx].buffers[lod].as_ref().expect("path wasn't filled in"), strokes[idx].blend, scissor))
+            }).collect::<Vec<_>>();
+
+            self.layers.draw(&self.context, &mut target, &self.tile_layer, &layered, &tile_uni);
+        }
+    }
+}
+
+impl Renderer for GlRenderer {
+    fn font(&self) -> &Font { self.text_blitter.font() }
+
+    // NOTE: the window decides how big the frame is, not us
+    fn begin_frame(&mut self, _dim: (u32, u32), clear: [f32; 4]) {
+        let mut target = self.context.draw();
+        target.clear_color(clear[0], clear[1], clear[2], clear[3]);
+        self.target = Some(target);
+    }
+
+    fn set_antialiasing(&mut self, enabled: bool) {
+        if enabled == self.antialiasing { return }
+
+        self.antialiasing = enabled;
+        self.tiles.invalidate();
+    }
+
+    fn draw_background(&mut self, background: Background, view: &View) {
+        let ofs = view.ofs();
+        let background_uni = uniform! {
+            dim:      [view.dim.0 as f32, view.dim.1 as f32],
+            ofs:      ofs,
+            scale:    view.scale,
+            rotation: view.rotation,
+
+            style:          background.shader_style(),
+            grid_px:        background::GRID_PX,
+            major_every:    background::MAJOR_EVERY,
+            dot_px:         background::DOT_PX,
+            min_spacing_px: background::MIN_SPACING_PX,
+
+            paper: COLOR_BG.to_rgba(),
+            minor: COLOR_GRID_MINOR.to_rgba(),
+            major: COLOR_GRID_MAJOR.to_rgba(),
+        };
+
+        let target = self.target.as_mut().expect("drawing outside of a frame");
+        target.draw(&self.layers.layer_quad, self.indices, &self.background_program, &background_uni, &Default::default())
+            .expect("could not blit background");
+    }
+
+    fn draw_strokes(&mut self, strokes: &[Stroke], view: &View) {
+        self.sync_paths(strokes);
+        self.tiles.sync(strokes, view.dim);
+
+        for (idx, (path, stroke)) in self.paths.iter_mut().zip(strokes).enumerate() {
+            if path.recolor(stroke) { self.tiles.redraw_stroke(idx); }
+        }
+
+        let visible = tiles::visible_tiles(view);
+        if visible.len() > MAX_VISIBLE_TILES {
+            let ofs = view.ofs();
+            let path_uni = uniform! {
+                dim:       [view.dim.0 as f32, view.dim.1 as f32],
+                ofs:       [ofs[0], ofs[1], 0.0f32],
+                scale:     [view.scale, view.scale],
+                rotation:  view.rotation,
+                antialias: self.antialiasing,
+            };
+
+            for (path, stroke) in self.paths.iter_mut().zip(strokes) {
+                path.draw(&self.context, view.dim, stroke, lod::level_for(view.scale, stroke.scale));
+            }
+
+            if self.screen.as_ref().is_none_or(|screen| screen.dim != view.dim) {
+                self.screen = Some(ScreenLayers {
+                    dim:   view.dim,
+                    ink:   empty_layer(&self.context, view.dim),
+                    layer: empty_layer(&self.context, view.dim),
+                });
+            }
+
+            // NOTE: strokes are blended together onto a layer of ink first,
+            //       same as they would be in a tile. strokes which are off
+            //       the screen are skipped.
+            let screen  = self.screen.as_ref().expect("screen layers were just allocated");
+            let tiles   = &self.tiles;
+            let layered = self.paths.iter().zip(strokes).enumerate().filter_map(|(idx, (path, stroke))| {
+                let bounds  = tiles.stroke_bounds(idx).expect("tiles weren't synced w/ the strokes");
+                let scissor = scissor_for(bounds, view.dim, |pos| view.apply([pos[0], pos[1], 0.0]))?;
+                let lod     = lod::level_for(view.scale, stroke.scale);
+                Some((path.buffers[lod].as_ref().expect("path wasn't filled in"), stroke.blend, scissor))
+            }).collect::<Vec<_>>();
+
+            {
+                let mut ink = SimpleFrameBuffer::new(&self.context, &screen.ink)
+                    .expect("could not draw to ink layer");
+
+                ink.clear_color(0.0, 0.0, 0.0, 0.0);
+                self.layers.draw(&self.context, &mut ink, &screen.layer, &layered, &path_uni);
+            }
+
+            let target = self.target.as_mut().expect("drawing outside of a frame");
+            for blend in composite_blends(BlendMode::Normal) {
+                self.layers.blit(target, &screen.ink, blend, None);
+            }
+
+            return;
+        }
+
+        // (re)draw any tiles which are missing or out of date, empty
+        // tiles are remembered as such so they needn't be checked again.
+        // NOTE: a tile which was drawn before is redrawn in the same slot
+        let mut taken = self.tiles.contents().cloned().collect::<HashSet<_>>();
+        for &key in &visible {
+            if !self.tiles.needs_render(key) { continue }
+
+            let strokes_in = self.tiles.strokes_in(key);
+            let content = if strokes_in.is_empty() { None } else {
+                let slot = match self.tiles.get(key) {
+                    Some(&slot) => slot,
+                    None => self.atlas.alloc(&self.context, &mut taken),
+                };
+
+                self.render_tile(key, slot, &strokes_in, strokes, view.dim);
+                Some(slot)
+            };
+
+            self.tiles.insert(key, content);
+        }
+
+        // NOTE: each tile is stretched over its corner of stroke space, which
+        //       the view then moves onto the screen, same as the strokes.
+        let mut drawn = visible.iter().filter_map(|&key| self.tiles.get(key).map(|&slot| (slot, key))).collect::<Vec<_>>();
+        drawn.sort_by_key(|&(slot, _)| slot.page);
+
+        self.tile_quads.clear();
+        for &(slot, key) in &drawn {
+            let b = key.bounds(view.dim);
+            let corner = |x: f32, y: f32, u: f32, v: f32| AtlasVert { pos: view.apply([x, y, 0.0]), uv: [u, v], layer: slot.layer as f32 };
+
+            self.tile_quads.extend_from_slice(&[
+                corner(b[0], b[1], 0.0, 0.0), corner(b[2], b[1], 1.0, 0.0), corner(b[0], b[3], 0.0, 1.0),
+                corner(b[0], b[3], 0.0, 1.0), corner(b[2], b[3], 1.0, 1.0), corner(b[2], b[1], 1.0, 0.0),
+            ]);
+        }
+
+        if !self.tile_quads.is_empty() {
+            let quads = self.tile_vbuf.slice(0..self.tile_quads.len()).expect("too many tile quads");
+            quads.write(&self.tile_quads);
+        }
+
+        // one draw for each page of the atlas that any of the tiles are on,
+        // they're sorted by page so each page's quads are all together.
+        let target = self.target.as_mut().expect("drawing outside of a frame");
+        for (page_idx, page) in self.atlas.pages.iter().enumerate() {
+            let first = drawn.iter().take_while(|&&(slot, _)| slot.page < page_idx).count();
+            let count = drawn[first..].iter().take_while(|&&(slot, _)| slot.page == page_idx).count();
+            if count == 0 { continue }
+
+            let quads = self.tile_vbuf.slice(first * 6 .. (first + count) * 6).expect("tile quads out of range");
+            let atlas_uni = uniform! {
+                page: page.sampled()
+                    .minify_filter(MinifySamplerFilter::Linear)
+                    .magnify_filter(MagnifySamplerFilter::Linear)
+                    .wrap_function(SamplerWrapFunction::Clamp),
+            };
+
+            // NOTE: tiles are cleared to transparent black & strokes are blended
+            //       into them so their colors are already premultiplied, see `LayerPipeline`.
+            let over = blend_with((LinearBlendingFactor::One, LinearBlendingFactor::OneMinusSourceAlpha),
+                                  (LinearBlendingFactor::One, LinearBlendingFactor::OneMinusSourceAlpha));
+            target.draw(quads, self.indices, &self.atlas_program, &atlas_uni, &DrawParameters {
+                blend: over,
+                multisampling: self.antialiasing,
                 .. Default::default()
-            }).expect("could not blit character");
-        }
-    }
-
-    fn ascii_to_ofs(cp: char) -> (f32, f32) {
-        assert!(cp.is_ascii());
-        let sprite_ofs = match cp {
-            'A'...'P' => (cp as u32 - 'A' as u32,      0),
-            'Q'...'Z' => (cp as u32 - 'Q' as u32,      1),
-            'a'...'f' => (cp as u32 - 'a' as u32 + 10, 1),
-            'g'...'v' => (cp as u32 - 'g' as u32,      2),
-            'w'...'z' => (cp as u32 - 'w' as u32,      3),
-
-            '1'...'9'  => ((cp  as u32 - '1' as u32) + 4, 3),
-            '0'        => (('9' as u32 - '0' as u32) + 4, 3),
-
-            ' ' => ( 0, 7),
-            '-' => (14, 3),
-            ',' => ( 0, 4),
-            '.' => ( 1, 4),
-            '@' => ( 5, 4),
-            '#' => ( 6, 4),
-            '(' => (12, 4),
-            ')' => (13, 4),
-            '=' => (15, 4),
-            ':' => ( 2, 5),
-            '[' => ( 8, 5),
-            ']' => ( 9, 5),
-            '<' => (14, 5),
-            '>' => (15, 5),
-
-            token => panic!("unhandled character in spritemap: {}", token),
+            }).expect("could not blit tiles");
+        }
+
+        self.tiles.evict(MAX_TILES);
+    }
+
+    fn draw_overlay(&mut self, verts: &[Vert2]) {
+        if verts.is_empty() { return }
+
+        // grow the overlay buffer if this frame has more than any before it
+        if verts.len() > self.overlay.len() {
+            self.overlay = glium::VertexBuffer::empty_dynamic(&self.context, verts.len().next_power_of_two())
+                .expect("could not alloc vbuf");
+        }
+
+        let batch = self.overlay.slice(0..verts.len())
+            .expect("overlay batch out of range");
+
+        batch.write(verts);
+
+        // NOTE: overlays are already in the unit square, so they're drawn
+        //       w/ a view which leaves them where they are.
+        let target = self.target.as_mut().expect("drawing outside of a frame");
+        let (win_w, win_h) = target.get_dimensions();
+        let overlay_uni = uniform! {
+            dim:      [win_w as f32, win_h as f32],
+            ofs:      [0.0f32, 0.0, 0.0],
+            scale:    [1.0f32, 1.0],
+            rotation: 0.0f32,
         };
 
-        let char_x: f32 =  sprite_ofs.0 as f32 * (1.0 / 16.0); // index into the page
-        let char_y: f32 =  sprite_ofs.1 as f32;                 // atlas page number
+        target.draw(batch, self.indices, &self.path_program, &overlay_uni, &DrawParameters {
+            blend: Blend::alpha_blending(),
+            multisampling: self.antialiasing,
+            .. Default::default()
+        }).expect("could not blit overlay");
+    }
 
-        (char_x, char_y)
+    fn draw_text(&mut self, layout: &TextLayout, ofs: (f32, f32), paint: &TextPaint) {
+        let target = self.target.as_mut().expect("drawing outside of a frame");
+        self.text_blitter.queue_layout(layout, ofs, paint);
+        self.text_blitter.flush(target);
+    }
+
+    fn end_frame(&mut self) {
+        if let Some(target) = self.target.take() {
+            target.finish()
+                .expect("could not render frame");
+        }
     }
 }
@@ -1,17 +1,51 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
+use glium::glutin::{ModifiersState, MouseButton, MouseScrollDelta};
 use glium::glutin::VirtualKeyCode as KeyCode;
 
-/// Responds to inquiries regarding three sets of keyboard input.
+use pen;
+use units::V2f;
+
+/// Scroll wheels which report in pixels are converted to lines of this many pixels
+static PIXELS_PER_LINE: f64 = 20.0;
+
+/// Responds to inquiries regarding three sets of keyboard & mouse button input.
 ///
 ///- Pressed keys
 ///- Released keys
 ///- Held keys
+///
+/// As well as the cursor's position, how far it & the scroll wheel moved
+/// this frame, and which modifiers are currently held. Pen tablets also
+/// report pressure & tilt, see `pen_pressure()` and `pen_tilt()`.
 #[derive(Default)]
 pub struct Input {
     pressed_keys:   HashMap<u32, bool>,
     released_keys:  HashMap<u32, bool>,
     held_keys:      HashMap<u32, bool>,
+
+    pressed_buttons:  HashMap<MouseButton, bool>,
+    released_buttons: HashMap<MouseButton, bool>,
+    held_buttons:     HashMap<MouseButton, bool>,
+
+    cursor:       Option<V2f>,
+    cursor_delta: V2f,
+    wheel_delta:  V2f,
+    modifiers:    ModifiersState,
+
+    // analog axes of each pointing device, & the last one which was a pen
+    devices: HashMap<u64, Device>,
+    pen:     Option<u64>,
+}
+
+/// What a pointing device has reported on its analog axes
+#[derive(Default)]
+struct Device {
+    // latest value of each axis since the last button press or release
+    axes: HashMap<u32, f64>,
+
+    // every axis it has ever reported, see `pen::is_pen`
+    seen: HashSet<u32>,
 }
 
 impl Input {
@@ -19,10 +53,16 @@ impl Input {
         Default::default()
     }
 
-    /// Resets the toggle states of pressed & released keys.
+    /// Resets the toggle states of pressed & released keys and buttons,
+    /// as well as the cursor & wheel deltas.
     pub fn begin_new_frame(&mut self) {
         self.pressed_keys.clear();
         self.released_keys.clear();
+        self.pressed_buttons.clear();
+        self.released_buttons.clear();
+
+        self.cursor_delta = V2f(0.0, 0.0);
+        self.wheel_delta  = V2f(0.0, 0.0);
     }
 
     /// Handles a key down event
@@ -37,6 +77,67 @@ impl Input {
         self.held_keys.insert(key as u32, false);
     }
 
+    /// Handles a mouse button down event
+    pub fn button_down_event(&mut self, button: MouseButton) {
+        self.forget_axes();
+        self.pressed_buttons.insert(button, true);
+        self.held_buttons.insert(button, true);
+    }
+
+    /// Handles a mouse button up event
+    pub fn button_up_event(&mut self, button: MouseButton) {
+        self.forget_axes();
+        self.released_buttons.insert(button, true);
+        self.held_buttons.insert(button, false);
+    }
+
+    // NOTE: a mouse doesn't report any pressure, so forget what the pen said
+    //       when it touches down or lifts off, or mice would inherit it.
+    fn forget_axes(&mut self) {
+        for device in self.devices.values_mut() {
+            device.axes.clear();
+        }
+    }
+
+    /// Handles the cursor moving to `pos` (in pixels from the upper left of the window)
+    pub fn cursor_moved_event(&mut self, pos: V2f) {
+        if let Some(prev) = self.cursor {
+            self.cursor_delta = V2f(self.cursor_delta.0 + (pos.0 - prev.0),
+                                    self.cursor_delta.1 + (pos.1 - prev.1));
+        }
+
+        self.cursor = Some(pos);
+    }
+
+    /// Handles the scroll wheel moving, deltas are accumulated in lines
+    pub fn wheel_event(&mut self, delta: MouseScrollDelta) {
+        let (dx, dy) = match delta {
+            MouseScrollDelta::LineDelta(dx, dy) => (dx as f64, dy as f64),
+            MouseScrollDelta::PixelDelta(pos)   => (pos.x / PIXELS_PER_LINE, pos.y / PIXELS_PER_LINE),
+        };
+
+        self.wheel_delta = V2f(self.wheel_delta.0 + dx, self.wheel_delta.1 + dy);
+    }
+
+    /// Handles motion on some analog axis of a pointing device (i.e: a pen's pressure)
+    pub fn axis_event(&mut self, device: u64, axis: u32, value: f64) {
+        let axes = self.devices.entry(device).or_default();
+        axes.axes.insert(axis, value);
+        axes.seen.insert(axis);
+
+        if pen::is_pen(&axes.seen) { self.pen = Some(device) }
+    }
+
+    /// Axes of the last pen to report any, see `pen::is_pen`
+    fn pen_axes(&self) -> Option<&HashMap<u32, f64>> {
+        self.pen.and_then(|device| self.devices.get(&device)).map(|device| &device.axes)
+    }
+
+    /// Handles any event which reports the current state of the modifier keys
+    pub fn modifiers_event(&mut self, modifiers: ModifiersState) {
+        self.modifiers = modifiers;
+    }
+
     /// Responds true if key was pressed since last call to `beginNewFrame()`.
     /// Responds false otherwise.
     pub fn was_key_pressed(&self, key: KeyCode) -> bool {
@@ -68,4 +169,53 @@ impl Input {
             None             => false,
         }
     }
+
+    /// Responds true if button was pressed since last call to `beginNewFrame()`.
+    /// Responds false otherwise.
+    pub fn was_button_pressed(&self, button: MouseButton) -> bool {
+        *self.pressed_buttons.get(&button).unwrap_or(&false)
+    }
+
+    /// Responds true if button was released since last call to `beginNewFrame()`.
+    /// Responds false otherwise.
+    pub fn was_button_released(&self, button: MouseButton) -> bool {
+        *self.released_buttons.get(&button).unwrap_or(&false)
+    }
+
+    /// Responds true if button has been pressed but _has not yet been released._
+    /// Responds false otherwise.
+    pub fn is_button_held(&self, button: MouseButton) -> bool {
+        *self.held_buttons.get(&button).unwrap_or(&false)
+    }
+
+    /// Position of the cursor in pixels from the upper left of the window,
+    /// this is the origin until the cursor has entered the window.
+    pub fn cursor(&self) -> V2f {
+        self.cursor.unwrap_or(V2f(0.0, 0.0))
+    }
+
+    /// How far (in pixels) the cursor moved since last call to `beginNewFrame()`
+    pub fn cursor_delta(&self) -> V2f { self.cursor_delta }
+
+    /// How far (in lines) the wheel scrolled since last call to `beginNewFrame()`
+    pub fn wheel_delta(&self) -> V2f { self.wheel_delta }
+
+    /// Modifier keys which are currently held down
+    pub fn modifiers(&self) -> ModifiersState { self.modifiers }
+
+    /// Pressure (`0.0 ..= 1.0`) of the pen touching the tablet, or `None` if
+    /// no pen has reported any since the last button press or release.
+    pub fn pen_pressure(&self) -> Option<f32> {
+        let axes = self.pen_axes()?;
+        axes.get(&pen::AXIS_PRESSURE).map(|&value| pen::normalize_pressure(value))
+    }
+
+    /// Tilt (`-1.0 ..= 1.0` along each axis) of the pen, if the tablet reports it.
+    pub fn pen_tilt(&self) -> Option<V2f> {
+        let axes = self.pen_axes()?;
+        match (axes.get(&pen::AXIS_TILT_X), axes.get(&pen::AXIS_TILT_Y)) {
+            (Some(&x), Some(&y)) => Some(pen::normalize_tilt(x, y)),
+            _ => None,
+        }
+    }
 }
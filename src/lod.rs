@@ -0,0 +1,148 @@
+//! Simplified copies of strokes, for drawing them when zoomed out.
+//!
+//! Strokes are drawn as a dab (a little square) per sample, and when the view
+//! is zoomed out most of those dabs land on the same few pixels. Each stroke
+//! keeps a few simplified copies of its samples (see `simplify`) & the view
+//! picks the coarsest one which is still w/in `MAX_ERROR_PX` of the original.
+
+use canvas::ControlPoint;
+
+/// How far (in the pixels of the stroke) each level of detail may stray from
+/// the full stroke, level `n` is simplified w/ `LOD_TOLERANCES[n - 1]`.
+pub static LOD_TOLERANCES: [f32; 5] = [1.0, 2.0, 4.0, 8.0, 16.0];
+
+/// How far a simplified stroke may stray from the original on screen (in pixels)
+static MAX_ERROR_PX: f32 = 0.5;
+
+/// Picks the level of detail for a stroke drawn at `stroke_scale`, which is
+/// being looked at zoomed to `zoom`. Level `0` is the stroke as drawn.
+pub fn level_for(zoom: f32, stroke_scale: f32) -> usize {
+    // NOTE: a pixel of the stroke covers this many pixels on the screen
+    let magnify = (zoom / stroke_scale).abs();
+
+    LOD_TOLERANCES.iter()
+        .take_while(|&&tolerance| tolerance * magnify <= MAX_ERROR_PX)
+        .count()
+}
+
+/// Every level of detail past the first, for a stroke w/ `dab_px` sized dabs
+pub fn build(samples: &[ControlPoint], dab_px: f32) -> Vec<Vec<ControlPoint>> {
+    LOD_TOLERANCES.iter()
+        .map(|&tolerance| simplify(samples, tolerance, dab_px))
+        .collect()
+}
+
+fn distance(lhs: &ControlPoint, rhs: &ControlPoint) -> f32 {
+    let (dx, dy) = ((rhs.screen_xy.0 - lhs.screen_xy.0) as f32, (rhs.screen_xy.1 - lhs.screen_xy.1) as f32);
+    (dx * dx + dy * dy).sqrt()
+}
+
+/// Distance from `point` to the segment between `start` & `end`
+fn distance_to_segment(point: &ControlPoint, start: &ControlPoint, end: &ControlPoint) -> f32 {
+    let (ax, ay) = (start.screen_xy.0 as f32, start.screen_xy.1 as f32);
+    let (bx, by) = (end.screen_xy.0 as f32, end.screen_xy.1 as f32);
+    let (px, py) = (point.screen_xy.0 as f32, point.screen_xy.1 as f32);
+
+    let (dx, dy) = (bx - ax, by - ay);
+    let len_sq = dx * dx + dy * dy;
+    if len_sq == 0.0 { return distance(point, start) }
+
+    let t = (((px - ax) * dx + (py - ay) * dy) / len_sq).clamp(0.0, 1.0);
+    let (cx, cy) = (ax + t * dx, ay + t * dy);
+    ((px - cx) * (px - cx) + (py - cy) * (py - cy)).sqrt()
+}
+
+/// Drops samples which don't change the shape of the stroke by more than
+/// `tolerance` pixels, w/ the Ramer-Douglas-Peucker algorithm.
+///
+/// NOTE: since each sample is drawn as a dab, the samples which are kept are
+///       never further apart than the dabs are wide (`dab_px` at full width)
+///       or `tolerance`, whichever is larger. Otherwise the line would fall
+///       apart into dots.
+pub fn simplify(samples: &[ControlPoint], tolerance: f32, dab_px: f32) -> Vec<ControlPoint> {
+    if samples.len() < 3 { return samples.to_vec() }
+
+    let last = samples.len() - 1;
+    let mut keep = vec![false; samples.len()];
+    keep[0] = true;
+    keep[last] = true;
+
+    let mut spans = vec![(0, last)];
+    while let Some((first, last)) = spans.pop() {
+        if last - first < 2 { continue }
+
+        let (start, end) = (&samples[first], &samples[last]);
+        let (farthest, error) = (first + 1..last)
+            .map(|idx| (idx, distance_to_segment(&samples[idx], start, end)))
+            .fold((first + 1, -1.0), |acc, (idx, error)| if error > acc.1 { (idx, error) } else { acc });
+
+        let max_span = tolerance.max(dab_px * start.width().min(end.width()));
+        let split = if error > tolerance {
+            farthest
+        } else if distance(start, end) > max_span {
+            (first + last) / 2
+        } else {
+            continue
+        };
+
+        keep[split] = true;
+        spans.push((first, split));
+        spans.push((split, last));
+    }
+
+    samples.iter().zip(keep).filter(|&(_, keep)| keep).map(|(sample, _)| *sample).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use units::V2;
+
+    fn points(xy: &[(i64, i64)]) -> Vec<ControlPoint> {
+        xy.iter().map(|&(x, y)| ControlPoint { screen_xy: V2(x, y), pressure: 1.0, tilt: None }).collect()
+    }
+
+    fn positions(samples: &[ControlPoint]) -> Vec<(i64, i64)> {
+        samples.iter().map(|sample| (sample.screen_xy.0, sample.screen_xy.1)).collect()
+    }
+
+    #[test]
+    fn straight_lines_keep_their_dabs_touching() {
+        let line = points(&(0..100).map(|x| (x * 2, 50)).collect::<Vec<_>>());
+        let simple = simplify(&line, 1.0, 7.5);
+
+        assert!(simple.len() < 40, "kept {} samples", simple.len());
+        assert_eq!(simple.first(), line.first());
+        assert_eq!(simple.last(), line.last());
+        for pair in simple.windows(2) {
+            assert!(distance(&pair[0], &pair[1]) <= 7.5, "{:?} is too far apart", positions(pair));
+        }
+    }
+
+    #[test]
+    fn corners_survive() {
+        let corner = points(&[(0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (4, 1), (4, 2), (4, 3), (4, 4)]);
+        assert_eq!(positions(&simplify(&corner, 0.5, 100.0)), vec![(0, 0), (4, 0), (4, 4)]);
+
+        // unless they're smaller than the tolerance
+        assert_eq!(positions(&simplify(&corner, 10.0, 100.0)), vec![(0, 0), (4, 4)]);
+    }
+
+    #[test]
+    fn short_strokes_are_left_alone() {
+        let dot = points(&[(3, 3), (4, 4)]);
+        assert_eq!(simplify(&dot, 16.0, 7.5), dot);
+    }
+
+    #[test]
+    fn zooming_out_lowers_the_detail() {
+        assert_eq!(level_for(1.0, 1.0), 0);
+        assert_eq!(level_for(0.5, 1.0), 1);
+        assert_eq!(level_for(0.25, 1.0), 2);
+        assert_eq!(level_for(0.01, 1.0), LOD_TOLERANCES.len());
+
+        // strokes drawn while zoomed in have more detail to spare
+        assert_eq!(level_for(0.5, 2.0), 2);
+        assert_eq!(level_for(-0.5, 1.0), 1);
+    }
+}
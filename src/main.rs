@@ -1,17 +1,122 @@
 #[macro_use] extern crate glium;
+extern crate dirs;
+extern crate rusttype;
+extern crate toml;
 
+pub mod background;
+pub mod bindings;
+pub mod bookmarks;
+pub mod camera;
+pub mod canvas;
 pub mod engine;
+pub mod font;
+pub mod gesture;
+#[cfg(test)]
+mod golden;
+#[allow(deprecated)] // NOTE: glium 0.22's `implement_vertex!` expands to `mem::uninitialized`
 pub mod graphics;
 pub mod input;
+pub mod lod;
+pub mod minimap;
+pub mod pen;
+pub mod record;
+pub mod render;
+pub mod session;
+pub mod snap;
+pub mod soft;
+pub mod text;
+pub mod tiles;
 pub mod units;
 pub mod util;
 
+use std::env;
+
+use std::process;
+
+use background::Background;
+use bindings::Bindings;
+use canvas::Canvas;
 use engine::Engine;
+use font::Font;
 use glium::glutin;
+use record::{Recorder, Recording};
+use render::{Renderer, COLOR_CLEAR};
+use session::Session;
+use soft::SoftRenderer;
+
+static DEFAULT_FONT: &str = "./simple-font.tga";
+static MSAA_SAMPLES: u16 = 4;
 
 fn main() {
     println!("koko is starting up...");
-    let context    = glutin::ContextBuilder::new();
+
+    // TODO: real argument parsing once we grow more than one flag
+    let mut args = env::args().skip(1);
+    let mut font_path = String::from(DEFAULT_FONT);
+    let mut record_path = None;
+    let mut replay_path = None;
+    let mut export_path = None;
+    let mut headless = false;
+    let mut antialiasing = true;
+    let mut background = None;
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--font"     => font_path   = args.next().expect("--font requires a path"),
+            "--record"   => record_path = Some(args.next().expect("--record requires a path")),
+            "--replay"   => replay_path = Some(args.next().expect("--replay requires a path")),
+            "--export"   => export_path = Some(args.next().expect("--export requires a path")),
+            "--headless" => headless = true,
+            "--no-antialiasing" => antialiasing = false,
+            "--background" => {
+                let name = args.next().expect("--background requires a style");
+                background = Background::from_name(&name).or_else(|| {
+                    println!("unknown background: {}, ignoring it", name);
+                    background
+                });
+            },
+            _ => println!("ignoring unknown argument: {}", arg),
+        }
+    }
+
+    let replay = replay_path.map(|path| Recording::load(&path).unwrap_or_else(|err| {
+        println!("could not load recording @ {}: {}", path, err);
+        process::exit(1);
+    }));
+
+    let font = font::load_font(&font_path).unwrap_or_else(|err| {
+        println!("could not load font @ {}: {}", font_path, err);
+        println!("falling back to {}", DEFAULT_FONT);
+        font::load_font(DEFAULT_FONT).expect("could not load default font")
+    });
+
+    let mut canvas = Canvas::new(Bindings::load_user_config());
+    canvas.antialiasing = antialiasing;
+
+    // NOTE: replays start from a fresh canvas (& don't save over the last
+    //       session) so they play out the same way they were recorded
+    let restore = replay.is_none();
+    if restore { Session::load_user().restore(&mut canvas); }
+    if let Some(background) = background { canvas.background = background; }
+
+    if headless {
+        let recording = replay.expect("--headless requires a recording to --replay");
+        run_headless(canvas, &recording, font, export_path);
+        return;
+    }
+
+    let recorder = record_path.and_then(|path| match Recorder::create(&path) {
+        Ok(recorder) => Some(recorder),
+        Err(err) => {
+            println!("could not record input to {}: {}", path, err);
+            None
+        },
+    });
+
+    // NOTE: multisampling only smooths the overlays (the cursor, minimap, etc.)
+    //       strokes are drawn into layers & tiles which aren't multisampled,
+    //       their edges are smoothed by the shader (see `Canvas::antialiasing`)
+    let samples    = if antialiasing { MSAA_SAMPLES } else { 0 };
+    let context    = glutin::ContextBuilder::new().with_multisampling(samples);
     let mut events = glutin::EventsLoop::new();
     let window     = glutin::WindowBuilder::new()
         .with_title(String::from("koko gl"))
@@ -22,7 +127,31 @@ fn main() {
 
 
     println!("let me tell you a story...");
-    let mut engine = Engine::new(display);
-    engine.run(&mut events);
+    let mut engine = Engine::new(display, font, canvas);
+    engine.run(&mut events, recorder, replay);
+    if restore { Session::of(engine.canvas()).save_user(); }
     println!("❤"); // TODO: emoji heart because I can?!
 }
+
+/// Plays a recording back w/o opening a window & reports how it ended up,
+/// the last frame is drawn in software & saved if there's an `export_path`.
+fn run_headless(mut canvas: Canvas, recording: &Recording, font: Font, export_path: Option<String>) {
+    record::replay(recording, &mut canvas);
+
+    println!("replayed {} frames", recording.frames.len());
+    println!("# paths: {}, # verts: {}", canvas.strokes.len(), canvas.verts());
+    println!("sb @ {:?}, scale @ {:?}, rot @ {:.0}deg", canvas.scanbox, canvas.scale, canvas.rotation.to_degrees());
+
+    if let Some(path) = export_path {
+        let mut renderer = SoftRenderer::new(font);
+        renderer.begin_frame(canvas.window_dim, COLOR_CLEAR);
+        render::draw_canvas(&mut renderer, &canvas);
+        render::draw_hud(&mut renderer, &canvas, None);
+        renderer.end_frame();
+
+        match renderer.save_tga(&path) {
+            Ok(()) => println!("exported last frame to {}", path),
+            Err(err) => println!("could not export frame to {}: {}", path, err),
+        }
+    }
+}
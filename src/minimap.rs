@@ -0,0 +1,160 @@
+//! A little map of the whole drawing in the corner of the screen, w/ the
+//! part of it that's on screen outlined. Clicking (or dragging) on it moves
+//! the view there.
+
+use camera;
+use canvas::Canvas;
+use graphics::Vert2;
+use lod::LOD_TOLERANCES;
+use render::{self, View};
+use units::V2f;
+
+/// Largest the minimap gets on the screen (in pixels) & how far it is from the corner
+pub static MINIMAP_PX:     (f32, f32) = (200.0, 120.0);
+pub static MINIMAP_MARGIN: f32 = 6.0;
+
+/// How much room is left around the drawing, as a fraction of its size
+static MINIMAP_PAD: f32 = 0.05;
+
+static COLOR_PANEL: [f32; 4] = [0.0, 0.0, 0.0, 0.63];
+static COLOR_INK:   [f32; 4] = [0.85, 0.85, 0.85, 0.9];
+static COLOR_VIEW:  [f32; 4] = [1.0, 1.0, 0.0, 1.0];
+
+/// Where the minimap is on the screen & what part of the world it shows
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Minimap {
+    /// `[x, y, w, h]` in pixels from the upper left of the screen
+    pub rect: [f32; 4],
+
+    /// the middle of the minimap, in world pixels (see `background`)
+    pub center: [f32; 2],
+
+    /// screen pixels per world pixel
+    pub zoom: f32,
+}
+
+/// `[min_x, min_y, max_x, max_y]` of the part of the world that's on screen
+fn view_bounds(view: &View) -> [f32; 4] {
+    let corners = [[-1.0, -1.0], [1.0, -1.0], [1.0, 1.0], [-1.0, 1.0]].iter()
+        .map(|&corner| view.unapply_world(corner))
+        .collect::<Vec<_>>();
+
+    corners.iter().fold([f32::MAX, f32::MAX, f32::MIN, f32::MIN], |acc, pos| {
+        [acc[0].min(pos[0]), acc[1].min(pos[1]), acc[2].max(pos[0]), acc[3].max(pos[1])]
+    })
+}
+
+impl Minimap {
+    /// Lays the minimap out in the lower right of the window, zoomed out
+    /// far enough to show every stroke & the view.
+    ///
+    /// NOTE: strokes are measured from their least detailed copy (see `lod`)
+    ///       which is close enough for a map this small.
+    pub fn new(canvas: &Canvas) -> Minimap {
+        // NOTE: it's shrunk down in small windows, so it doesn't cover them up
+        let (width, height) = (canvas.window_dim.0 as f32, canvas.window_dim.1 as f32);
+        let size = (MINIMAP_PX.0.min(width / 4.0), MINIMAP_PX.1.min(height / 4.0));
+        let rect = [width - size.0 - MINIMAP_MARGIN, height - size.1 - MINIMAP_MARGIN, size.0, size.1];
+
+        let view   = view_bounds(&canvas.view());
+        let bounds = match camera::bounds(&canvas.strokes, LOD_TOLERANCES.len(), canvas.window_dim) {
+            Some(ink) => [view[0].min(ink[0]), view[1].min(ink[1]), view[2].max(ink[2]), view[3].max(ink[3])],
+            None => view,
+        };
+
+        let size = [(bounds[2] - bounds[0]) * (1.0 + 2.0 * MINIMAP_PAD), (bounds[3] - bounds[1]) * (1.0 + 2.0 * MINIMAP_PAD)];
+        Minimap {
+            rect,
+            center: [(bounds[0] + bounds[2]) / 2.0, (bounds[1] + bounds[3]) / 2.0],
+            zoom:   (rect[2] / size[0].max(1.0)).min(rect[3] / size[1].max(1.0)),
+        }
+    }
+
+    pub fn contains(&self, pos: V2f) -> bool {
+        let (x, y) = (pos.0 as f32, pos.1 as f32);
+        x >= self.rect[0] && y >= self.rect[1] && x < self.rect[0] + self.rect[2] && y < self.rect[1] + self.rect[3]
+    }
+
+    /// Where a point on the minimap (in pixels on the screen) is in the world
+    pub fn world_at(&self, pos: V2f) -> [f32; 2] {
+        let (mid_x, mid_y) = (self.rect[0] + self.rect[2] / 2.0, self.rect[1] + self.rect[3] / 2.0);
+        [self.center[0] + (pos.0 as f32 - mid_x) / self.zoom,
+         self.center[1] - (pos.1 as f32 - mid_y) / self.zoom]
+    }
+
+    /// Where a point in the world is on the minimap (in pixels on the screen), undoing `world_at`
+    pub fn screen_at(&self, pos: [f32; 2]) -> V2f {
+        let (mid_x, mid_y) = (self.rect[0] + self.rect[2] / 2.0, self.rect[1] + self.rect[3] / 2.0);
+        V2f((mid_x + (pos[0] - self.center[0]) * self.zoom) as f64,
+            (mid_y - (pos[1] - self.center[1]) * self.zoom) as f64)
+    }
+}
+
+/// A solid quad (in pixels on the screen) as triangles in the unit square
+fn quad(corners: [V2f; 4], color: [f32; 4], dim: (u32, u32)) -> Vec<Vert2> {
+    [0, 1, 2, 2, 3, 0].iter().map(|&idx| {
+        let (x, y) = render::screen_to_unit(corners[idx], dim);
+        Vert2 { pos: [x, y, 0.0], color }
+    }).collect()
+}
+
+/// The minimap's panel, a dot for each (simplified) sample of every stroke
+/// & an outline around what's on the screen.
+pub fn minimap_verts(canvas: &Canvas, minimap: &Minimap) -> Vec<Vert2> {
+    let dim  = canvas.window_dim;
+    let rect = minimap.rect;
+    let mut verts = quad([V2f(rect[0] as f64, rect[1] as f64), V2f((rect[0] + rect[2]) as f64, rect[1] as f64),
+                          V2f((rect[0] + rect[2]) as f64, (rect[1] + rect[3]) as f64), V2f(rect[0] as f64, (rect[1] + rect[3]) as f64)],
+                         COLOR_PANEL, dim);
+
+    // NOTE: samples which land on the same pixel of the minimap are skipped
+    let coarse = LOD_TOLERANCES.len();
+    for stroke in &canvas.strokes {
+        let mut last = V2f(f64::MAX, f64::MAX);
+        for point in stroke.lod(coarse) {
+            let V2f(x, y) = minimap.screen_at(stroke.world_pos(point, dim));
+            if (x - last.0).abs() < 1.0 && (y - last.1).abs() < 1.0 { continue }
+            last = V2f(x, y);
+
+            verts.extend(quad([V2f(x - 1.0, y - 1.0), V2f(x + 1.0, y - 1.0), V2f(x + 1.0, y + 1.0), V2f(x - 1.0, y + 1.0)],
+                              COLOR_INK, dim));
+        }
+    }
+
+    // the view is outlined w/ a thin quad along each side, it's only a
+    // rectangle when the view isn't spun around.
+    let view    = canvas.view();
+    let corners = [[-1.0, -1.0], [1.0, -1.0], [1.0, 1.0], [-1.0, 1.0]].iter()
+        .map(|&corner| minimap.screen_at(view.unapply_world(corner)))
+        .collect::<Vec<_>>();
+
+    for idx in 0..corners.len() {
+        let (from, to) = (corners[idx], corners[(idx + 1) % corners.len()]);
+        let along = (to - from).norm();
+        let side  = V2f(-along.1 * 0.75, along.0 * 0.75);
+        verts.extend(quad([from - side, to - side, to + side, from + side], COLOR_VIEW, dim));
+    }
+
+    verts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bindings::Bindings;
+
+    #[test]
+    fn minimap_round_trips_world_positions() {
+        let canvas  = Canvas::new(Bindings::defaults());
+        let minimap = Minimap::new(&canvas);
+
+        let world = minimap.world_at(V2f(1200.0, 650.0));
+        let V2f(x, y) = minimap.screen_at(world);
+        assert!((x - 1200.0).abs() < 1e-3 && (y - 650.0).abs() < 1e-3, "came back as {}, {}", x, y);
+
+        // it fits the view on it, w/ a little room to spare
+        assert!(minimap.contains(minimap.screen_at([-640.0, 360.0])));
+        assert!(minimap.contains(minimap.screen_at([640.0, -360.0])));
+        assert!(!minimap.contains(V2f(10.0, 10.0)));
+    }
+}
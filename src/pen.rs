@@ -0,0 +1,85 @@
+use std::collections::HashSet;
+
+use units::V2f;
+
+// NOTE: winit doesn't know what a pen is, tablets just show up as extra
+//       analog axes on the pointer. these are the valuators X11 assigns to
+//       a wacom stylus (0 & 1 are its absolute x/y), other platforms don't
+//       report pen axes at all yet & fall back to `VelocityPressure`.
+//
+//       a device counts as a pen once it reports any pressure, tilt is up
+//       to the pen. (mice can have valuators here too, for their scroll
+//       wheels, but winit turns those into wheel events instead.)
+//
+pub static AXIS_PRESSURE: u32 = 2;
+pub static AXIS_TILT_X:   u32 = 3;
+pub static AXIS_TILT_Y:   u32 = 4;
+
+/// Full scale of the pressure axis, the wacom driver normalizes every pen to this
+static PRESSURE_MAX: f64 = 65535.0;
+
+/// Full scale of the tilt axes (in degrees away from upright)
+static TILT_MAX: f64 = 64.0;
+
+/// Speed (in pixels per second) at which simulated pressure bottoms out
+static SIM_MAX_SPEED: f64 = 4000.0;
+static SIM_MIN_PRESSURE: f64 = 0.2;
+
+/// How long (in seconds) simulated pressure takes to catch up to the cursor's speed
+static SIM_RESPONSE: f64 = 0.05;
+
+/// Responds true if a device which reported `axes` is a pen, see `AXIS_PRESSURE`
+pub fn is_pen(axes: &HashSet<u32>) -> bool {
+    axes.contains(&AXIS_PRESSURE)
+}
+
+/// Converts a raw pressure axis value into `0.0 ..= 1.0`
+pub fn normalize_pressure(value: f64) -> f32 {
+    (value / PRESSURE_MAX).clamp(0.0, 1.0) as f32
+}
+
+/// Converts raw tilt axis values into `-1.0 ..= 1.0` along each axis
+pub fn normalize_tilt(x: f64, y: f64) -> V2f {
+    V2f((x / TILT_MAX).clamp(-1.0, 1.0),
+        (y / TILT_MAX).clamp(-1.0, 1.0))
+}
+
+/// Fakes pen pressure for devices which can't report any (i.e: mice) from
+/// how quickly the cursor is moving; slow strokes press harder than flicks.
+pub struct VelocityPressure {
+    last:     Option<(V2f, f64)>,
+    pressure: f64,
+}
+
+impl VelocityPressure {
+    pub fn new() -> VelocityPressure {
+        VelocityPressure { last: None, pressure: 1.0 }
+    }
+
+    /// Forgets the previous stroke, should be called when a new one starts.
+    pub fn reset(&mut self) {
+        *self = VelocityPressure::new();
+    }
+
+    /// Simulates the pressure of the stroke when it reaches `pos`, `at` seconds in
+    pub fn sample(&mut self, pos: V2f, at: f64) -> f32 {
+        if let Some((last_pos, last_at)) = self.last {
+            let dt = at - last_at;
+            if dt > 0.0 {
+                let speed  = V2f(pos.0 - last_pos.0, pos.1 - last_pos.1).length() / dt;
+                let target = 1.0 - (speed / SIM_MAX_SPEED).min(1.0) * (1.0 - SIM_MIN_PRESSURE);
+
+                // ease towards the target so the width doesn't jitter w/ every sample
+                let blend = 1.0 - (-dt / SIM_RESPONSE).exp();
+                self.pressure += (target - self.pressure) * blend;
+            }
+        }
+
+        self.last = Some((pos, at));
+        self.pressure as f32
+    }
+}
+
+impl Default for VelocityPressure {
+    fn default() -> VelocityPressure { VelocityPressure::new() }
+}
@@ -0,0 +1,399 @@
+use std::collections::hash_map::DefaultHasher;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+
+use glium::glutin::dpi::LogicalPosition;
+use glium::glutin::{DeviceId, ElementState, ModifiersState, MouseButton, MouseScrollDelta, TouchPhase, WindowEvent};
+use glium::glutin::VirtualKeyCode as KeyCode;
+
+use bindings::key_from_name;
+use canvas::Canvas;
+use units::V2f;
+
+static HEADER: &str = "# koko input recording";
+
+/// The window events koko cares about, w/o any of the platform specific
+/// bits so they can be written down & played back. Device ids are only
+/// kept as a number (see `device_number`) where koko needs to tell them apart.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum InputEvent {
+    CloseRequested,
+    Resized(u32, u32),
+    CursorMoved(V2f, ModifiersState),
+    MouseInput(ElementState, MouseButton, ModifiersState),
+    MouseWheel(MouseScrollDelta, ModifiersState),
+    Key(ElementState, KeyCode, ModifiersState),
+    AxisMotion(u64, u32, f64),
+    Touch(u64, TouchPhase, V2f),
+}
+
+impl InputEvent {
+    /// Responds w/ `None` for events koko ignores
+    pub fn from_window_event(event: &WindowEvent) -> Option<InputEvent> {
+        match *event {
+            WindowEvent::CloseRequested => Some(InputEvent::CloseRequested),
+            WindowEvent::Resized(size) => Some(InputEvent::Resized(size.width as u32, size.height as u32)),
+
+            WindowEvent::CursorMoved { position, modifiers, .. } => {
+                Some(InputEvent::CursorMoved(V2f(position.x, position.y), modifiers))
+            },
+
+            WindowEvent::MouseInput { state, button, modifiers, .. } => Some(InputEvent::MouseInput(state, button, modifiers)),
+            WindowEvent::MouseWheel { delta, modifiers, .. } => Some(InputEvent::MouseWheel(delta, modifiers)),
+            WindowEvent::AxisMotion { device_id, axis, value } => Some(InputEvent::AxisMotion(device_number(device_id), axis, value)),
+
+            WindowEvent::KeyboardInput { input, .. } => {
+                input.virtual_keycode.map(|key| InputEvent::Key(input.state, key, input.modifiers))
+            },
+
+            WindowEvent::Touch(touch) => {
+                Some(InputEvent::Touch(touch.id, touch.phase, V2f(touch.location.x, touch.location.y)))
+            },
+
+            _ => None,
+        }
+    }
+}
+
+/// Writes an event as one line of a recording (w/o its timestamp)
+impl fmt::Display for InputEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            InputEvent::CloseRequested => write!(f, "close"),
+            InputEvent::Resized(width, height) => write!(f, "resize {} {}", width, height),
+            InputEvent::CursorMoved(pos, mods) => write!(f, "cursor {} {} {}", pos.0, pos.1, mods_name(mods)),
+
+            InputEvent::MouseInput(state, button, mods) => {
+                let button = match button {
+                    MouseButton::Left     => "left".to_string(),
+                    MouseButton::Right    => "right".to_string(),
+                    MouseButton::Middle   => "middle".to_string(),
+                    MouseButton::Other(n) => n.to_string(),
+                };
+
+                write!(f, "button {} {} {}", state_name(state), button, mods_name(mods))
+            },
+
+            InputEvent::MouseWheel(MouseScrollDelta::LineDelta(dx, dy), mods) => {
+                write!(f, "wheel lines {} {} {}", dx, dy, mods_name(mods))
+            },
+
+            InputEvent::MouseWheel(MouseScrollDelta::PixelDelta(pos), mods) => {
+                write!(f, "wheel pixels {} {} {}", pos.x, pos.y, mods_name(mods))
+            },
+
+            InputEvent::Key(state, key, mods) => write!(f, "key {} {:?} {}", state_name(state), key, mods_name(mods)),
+            InputEvent::AxisMotion(device, axis, value) => write!(f, "axis {} {} {}", device, axis, value),
+
+            InputEvent::Touch(id, phase, pos) => {
+                let phase = match phase {
+                    TouchPhase::Started   => "started",
+                    TouchPhase::Moved     => "moved",
+                    TouchPhase::Ended     => "ended",
+                    TouchPhase::Cancelled => "cancelled",
+                };
+
+                write!(f, "touch {} {} {} {}", id, phase, pos.0, pos.1)
+            },
+        }
+    }
+}
+
+/// Glutin's device ids are opaque, this turns one into a number which is
+/// the same for the whole time koko is running.
+fn device_number(device: DeviceId) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    device.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn state_name(state: ElementState) -> &'static str {
+    match state {
+        ElementState::Pressed  => "pressed",
+        ElementState::Released => "released",
+    }
+}
+
+fn mods_name(mods: ModifiersState) -> String {
+    let mut names = vec![];
+    if mods.ctrl  { names.push("ctrl");  }
+    if mods.shift { names.push("shift"); }
+    if mods.alt   { names.push("alt");   }
+    if mods.logo  { names.push("logo");  }
+
+    if names.is_empty() { "-".to_string() } else { names.join("+") }
+}
+
+#[derive(Debug)]
+pub enum RecordError {
+    Io(io::Error),
+    Malformed(usize, String),
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RecordError::Io(ref err) => write!(f, "i/o error reading recording: {}", err),
+            RecordError::Malformed(line, ref msg) => write!(f, "malformed recording @ line {}: {}", line, msg),
+        }
+    }
+}
+
+impl Error for RecordError {}
+
+impl From<io::Error> for RecordError {
+    fn from(err: io::Error) -> RecordError { RecordError::Io(err) }
+}
+
+/// An event, and when it arrived (in seconds since koko started)
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TimedEvent {
+    pub at:    f64,
+    pub event: InputEvent,
+}
+
+/// The events handled by a single frame, which started `at` seconds in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    pub at:     f64,
+    pub events: Vec<TimedEvent>,
+}
+
+/// A session's worth of input, frame by frame.
+///
+/// Recordings are plain text, one event per line (prefixed w/ its timestamp)
+/// and each frame is started by a `frame` line, e.g:
+///
+/// ```text
+/// # koko input recording
+/// 0.0083 frame
+/// 0.0071 cursor 640 360 -
+/// 0.0079 button pressed left ctrl
+/// 0.0166 frame
+/// 0.0152 key pressed Escape -
+/// ```
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Recording {
+    pub frames: Vec<Frame>,
+}
+
+impl Recording {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Recording, RecordError> {
+        let mut source = String::new();
+        File::open(path)?.read_to_string(&mut source)?;
+        Recording::parse(&source)
+    }
+
+    pub fn parse(source: &str) -> Result<Recording, RecordError> {
+        let mut frames: Vec<Frame> = vec![];
+
+        for (line_no, line) in source.lines().enumerate().map(|(idx, line)| (idx + 1, line.trim())) {
+            if line.is_empty() || line.starts_with('#') { continue }
+
+            let malformed = |msg: &str| RecordError::Malformed(line_no, msg.to_string());
+            let parts = line.split_whitespace().collect::<Vec<_>>();
+            let at = parts[0].parse::<f64>().map_err(|_| malformed("expected a timestamp"))?;
+
+            if parts.get(1) == Some(&"frame") {
+                frames.push(Frame { at, events: vec![] });
+                continue;
+            }
+
+            let event = parse_event(&parts[1..]).map_err(|msg| malformed(&msg))?;
+            match frames.last_mut() {
+                Some(frame) => frame.events.push(TimedEvent { at, event }),
+                None => return Err(malformed("event before the first frame")),
+            }
+        }
+
+        Ok(Recording { frames })
+    }
+
+    pub fn write_to<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "{}", HEADER)?;
+        for frame in &self.frames { write_frame(out, frame)?; }
+        Ok(())
+    }
+}
+
+fn write_frame<W: Write>(out: &mut W, frame: &Frame) -> io::Result<()> {
+    writeln!(out, "{} frame", frame.at)?;
+    for timed in &frame.events {
+        writeln!(out, "{} {}", timed.at, timed.event)?;
+    }
+
+    Ok(())
+}
+
+/// Parses the rest of an event line, after the timestamp
+fn parse_event(parts: &[&str]) -> Result<InputEvent, String> {
+    let float = |idx: usize| -> Result<f64, String> {
+        parts.get(idx).and_then(|part| part.parse::<f64>().ok())
+            .ok_or_else(|| format!("expected a number in argument {}", idx))
+    };
+
+    let int = |idx: usize| -> Result<u64, String> {
+        parts.get(idx).and_then(|part| part.parse::<u64>().ok())
+            .ok_or_else(|| format!("expected an integer in argument {}", idx))
+    };
+
+    let state = |idx: usize| -> Result<ElementState, String> {
+        match parts.get(idx) {
+            Some(&"pressed")  => Ok(ElementState::Pressed),
+            Some(&"released") => Ok(ElementState::Released),
+            _ => Err("expected pressed or released".to_string()),
+        }
+    };
+
+    let mods = |idx: usize| -> Result<ModifiersState, String> {
+        let mut mods = ModifiersState::default();
+        match parts.get(idx) {
+            Some(&"-") => {},
+            Some(names) => for name in names.split('+') {
+                match name {
+                    "ctrl"  => mods.ctrl  = true,
+                    "shift" => mods.shift = true,
+                    "alt"   => mods.alt   = true,
+                    "logo"  => mods.logo  = true,
+                    _ => return Err(format!("unknown modifier: {}", name)),
+                }
+            },
+
+            None => return Err("expected modifiers".to_string()),
+        }
+
+        Ok(mods)
+    };
+
+    match parts.first() {
+        Some(&"close")  => Ok(InputEvent::CloseRequested),
+        Some(&"resize") => Ok(InputEvent::Resized(int(1)? as u32, int(2)? as u32)),
+        Some(&"cursor") => Ok(InputEvent::CursorMoved(V2f(float(1)?, float(2)?), mods(3)?)),
+
+        Some(&"button") => {
+            let button = match parts.get(2) {
+                Some(&"left")   => MouseButton::Left,
+                Some(&"right")  => MouseButton::Right,
+                Some(&"middle") => MouseButton::Middle,
+                _ => MouseButton::Other(int(2)? as u8),
+            };
+
+            Ok(InputEvent::MouseInput(state(1)?, button, mods(3)?))
+        },
+
+        Some(&"wheel") => {
+            let delta = match parts.get(1) {
+                Some(&"lines")  => MouseScrollDelta::LineDelta(float(2)? as f32, float(3)? as f32),
+                Some(&"pixels") => MouseScrollDelta::PixelDelta(LogicalPosition::new(float(2)?, float(3)?)),
+                _ => return Err("expected lines or pixels".to_string()),
+            };
+
+            Ok(InputEvent::MouseWheel(delta, mods(4)?))
+        },
+
+        Some(&"key") => {
+            let name = parts.get(2).ok_or_else(|| "expected a key".to_string())?;
+            let key  = key_from_name(name).ok_or_else(|| format!("unknown key: {}", name))?;
+            Ok(InputEvent::Key(state(1)?, key, mods(3)?))
+        },
+
+        Some(&"axis") => Ok(InputEvent::AxisMotion(int(1)?, int(2)? as u32, float(3)?)),
+
+        Some(&"touch") => {
+            let phase = match parts.get(2) {
+                Some(&"started")   => TouchPhase::Started,
+                Some(&"moved")     => TouchPhase::Moved,
+                Some(&"ended")     => TouchPhase::Ended,
+                Some(&"cancelled") => TouchPhase::Cancelled,
+                _ => return Err("expected a touch phase".to_string()),
+            };
+
+            Ok(InputEvent::Touch(int(1)?, phase, V2f(float(3)?, float(4)?)))
+        },
+
+        Some(name) => Err(format!("unknown event: {}", name)),
+        None => Err("expected an event".to_string()),
+    }
+}
+
+/// Writes frames to a recording file as they happen
+pub struct Recorder {
+    out: BufWriter<File>,
+}
+
+impl Recorder {
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Recorder> {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "{}", HEADER)?;
+        Ok(Recorder { out })
+    }
+
+    pub fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
+        write_frame(&mut self.out, frame)
+    }
+}
+
+/// Plays a recording back into a canvas, frame by frame, w/o a window.
+/// Stops early if the recording quit koko.
+pub fn replay(recording: &Recording, canvas: &mut Canvas) {
+    for frame in &recording.frames {
+        if !canvas.is_running { break }
+        canvas.step(frame);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_every_kind_of_event() {
+        let ctrl_shift = ModifiersState { ctrl: true, shift: true, ..Default::default() };
+        let events = vec![
+            InputEvent::CloseRequested,
+            InputEvent::Resized(1920, 1080),
+            InputEvent::CursorMoved(V2f(640.25, 0.1), ModifiersState::default()),
+            InputEvent::MouseInput(ElementState::Pressed, MouseButton::Left, ctrl_shift),
+            InputEvent::MouseInput(ElementState::Released, MouseButton::Other(7), ModifiersState::default()),
+            InputEvent::MouseWheel(MouseScrollDelta::LineDelta(0.0, -1.5), ModifiersState::default()),
+            InputEvent::MouseWheel(MouseScrollDelta::PixelDelta(LogicalPosition::new(3.0, 4.0)), ctrl_shift),
+            InputEvent::Key(ElementState::Pressed, KeyCode::Escape, ctrl_shift),
+            InputEvent::AxisMotion(7, 2, 31337.5),
+            InputEvent::Touch(42, TouchPhase::Cancelled, V2f(1.0 / 3.0, 2.0)),
+        ];
+
+        let recording = Recording {
+            frames: vec![
+                Frame { at: 0.0, events: vec![] },
+                Frame { at: 0.1 + 0.2, events: events.iter().enumerate()
+                    .map(|(idx, &event)| TimedEvent { at: idx as f64 * 0.001, event })
+                    .collect() },
+            ],
+        };
+
+        let mut text = vec![];
+        recording.write_to(&mut text).unwrap();
+        let parsed = Recording::parse(&String::from_utf8(text).unwrap()).unwrap();
+        assert_eq!(parsed, recording);
+    }
+
+    #[test]
+    fn rejects_events_before_the_first_frame() {
+        match Recording::parse("0.1 cursor 1 2 -\n0.2 frame") {
+            Err(RecordError::Malformed(1, _)) => {},
+            other => panic!("expected an error on line 1: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        match Recording::parse("0.0 frame\n0.1 key pressed Banana -") {
+            Err(RecordError::Malformed(2, msg)) => assert!(msg.contains("Banana")),
+            other => panic!("expected an error on line 2: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,374 @@
+use std::time::Duration;
+
+use background::Background;
+use bindings::{Action, Bindings};
+use canvas::{BlendMode, BrushMode, Canvas, ControlPoint, Stroke};
+use font::Font;
+use graphics::Vert2;
+use minimap;
+use text::{self, Align, Anchor, Span, TextLayout, TextPaint, TextStyle};
+use units::{Color, V2, V2f};
+
+/// Width of a stroke's dabs at full pressure, in pixels
+pub static DAB_PX: f32 = 7.5;
+
+pub static HUD_TEXT_SIZE: f32 = 12.0;
+pub static HUD_MARGIN:    f32 =  6.0;
+
+pub static COLOR_BG:  Color = Color::RGB(13,13,13);
+pub static COLOR_GRID_MINOR: Color = Color::RGBA(255,255,255,20);
+pub static COLOR_GRID_MAJOR: Color = Color::RGBA(255,255,255,48);
+pub static COLOR_CLEAR: [f32; 4] = [0.05, 0.05, 0.05, 1.0];
+pub static COLOR_FPS: Color = Color::RGB(255,255,0);
+pub static COLOR_FPS_SLOW: Color = Color::RGB(255,40,40);
+pub static COLOR_HUD: Color = Color::RGB(255,255,255);
+pub static COLOR_HUD_PANEL:  Color = Color::RGBA(0,0,0,160);
+pub static COLOR_HUD_SHADOW: Color = Color::RGBA(0,0,0,255);
+
+/// Ink new strokes start out with, the hue keys (I/O/P) change it
+pub static COLOR_PEN: (u8, u8, u8) = (191, 0, 128);
+
+static COLOR_PENDING: [f32; 4] = [1.0, 0.0, 1.0, 1.0];
+static COLOR_CURSOR:  [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+
+/// Highlighters are a flat, translucent yellow no matter how hard you press
+static COLOR_HIGHLIGHTER: [f32; 3] = [1.0, 0.9, 0.2];
+static HIGHLIGHTER_ALPHA: f32 = 0.5;
+
+/// Something which can draw a frame of koko, i.e: the GPU (`graphics::GlRenderer`)
+/// or a plain old buffer of pixels (`soft::SoftRenderer`.)
+///
+/// Positions handed to a renderer are either in the GL's unit square (from
+/// `-1.0` in the lower left to `1.0` in the upper right of the screen) or, for
+/// text, in pixels from the upper left of the screen.
+pub trait Renderer {
+    /// The font text is laid out & drawn with
+    fn font(&self) -> &Font;
+
+    /// Starts a fresh `dim` sized frame, filled w/ `clear`
+    fn begin_frame(&mut self, dim: (u32, u32), clear: [f32; 4]);
+
+    /// Smooths (or stops smoothing) the edges of strokes drawn from now on
+    fn set_antialiasing(&mut self, enabled: bool);
+
+    /// Covers the whole frame w/ the background, as seen through `view`
+    fn draw_background(&mut self, background: Background, view: &View);
+
+    /// Draws every finished stroke as seen through `view`. Renderers may
+    /// cache what they drew: strokes are added to the end of the list (or
+    /// all removed) and otherwise only change color in place (see
+    /// `Action::RecolorStroke`), which has to redraw whatever the stroke touched.
+    ///
+    /// NOTE: each stroke is drawn onto a layer of its own, where overlapping
+    ///       dabs keep whichever is more opaque (so a stroke's opacity stays
+    ///       even) and then the layer is blended onto the strokes below it,
+    ///       see `composite`. Strokes are blended together before any of
+    ///       them are blended onto the frame, so they're always w/ premultiplied alpha.
+    fn draw_strokes(&mut self, strokes: &[Stroke], view: &View);
+
+    /// Draws a list of triangles which are already in the unit square
+    fn draw_overlay(&mut self, verts: &[Vert2]);
+
+    /// Draws a block of text w/ its upper left corner at `ofs` (in pixels)
+    fn draw_text(&mut self, layout: &TextLayout, ofs: (f32, f32), paint: &TextPaint);
+
+    fn end_frame(&mut self);
+}
+
+/// Where the canvas is being looked at from
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct View {
+    pub dim:      (u32, u32),
+    pub scale:    f32,
+    pub scanbox:  V2,
+    pub rotation: f32,
+}
+
+impl View {
+    /// offset of the scanbox converted to the screen space unit square
+    pub fn ofs(&self) -> [f32; 2] {
+        let V2(ofs_x, ofs_y) = self.scanbox;
+        [-(ofs_x as f32) / self.dim.0 as f32, -(ofs_y as f32) / self.dim.1 as f32]
+    }
+
+    /// Moves a point of a stroke onto the screen, this is what `flat.v.glsl` does
+    pub fn apply(&self, pos: [f32; 3]) -> [f32; 2] {
+        let ofs = self.ofs();
+        let (x, y) = (pos[0] * self.scale + ofs[0], pos[1] * self.scale + ofs[1]);
+
+        // spin around the center of the window in pixels, so we don't squash the paths
+        let (half_w, half_h) = (self.dim.0 as f32 / 2.0, self.dim.1 as f32 / 2.0);
+        let (px_x, px_y) = (x * half_w, y * half_h);
+        let (sin, cos) = self.rotation.sin_cos();
+
+        [(cos * px_x + sin * px_y) / half_w, (cos * px_y - sin * px_x) / half_h]
+    }
+
+    /// Moves a point on the screen back to where it'd be in a stroke, undoing `apply`
+    pub fn unapply(&self, pos: [f32; 2]) -> [f32; 2] {
+        let (half_w, half_h) = (self.dim.0 as f32 / 2.0, self.dim.1 as f32 / 2.0);
+        let (px_x, px_y) = (pos[0] * half_w, pos[1] * half_h);
+        let (sin, cos) = self.rotation.sin_cos();
+
+        let ofs = self.ofs();
+        let (x, y) = ((cos * px_x - sin * px_y) / half_w, (sin * px_x + cos * px_y) / half_h);
+        [(x - ofs[0]) / self.scale, (y - ofs[1]) / self.scale]
+    }
+
+    /// Where a point on the screen is in world pixels (see `background`)
+    pub fn unapply_world(&self, pos: [f32; 2]) -> [f32; 2] {
+        let stroke = self.unapply(pos);
+        [stroke[0] * self.dim.0 as f32 / 2.0, stroke[1] * self.dim.1 as f32 / 2.0]
+    }
+
+    /// Moves a point in world pixels onto the screen, undoing `unapply_world`
+    pub fn apply_world(&self, pos: [f32; 2]) -> [f32; 2] {
+        self.apply([pos[0] / (self.dim.0 as f32 / 2.0), pos[1] / (self.dim.1 as f32 / 2.0), 0.0])
+    }
+}
+
+/// Converts a position in pixels from the upper left of the screen to the unit square
+pub fn screen_to_unit(pos: V2f, dim: (u32, u32)) -> (f32, f32) {
+    let adj_x = pos.0 / (dim.0 as f64 / 2.0);
+    let adj_y = pos.1 / (dim.1 as f64 / 2.0);
+    ( (adj_x - 1.0) as f32, -(adj_y - 1.0) as f32 )
+}
+
+/// Converts a position in the unit square to pixels from the upper left of the screen
+pub fn unit_to_screen(pos: [f32; 2], dim: (u32, u32)) -> V2f {
+    let adj_x = (pos[0] as f64 + 1.0) * (dim.0 as f64 / 2.0);
+    let adj_y = (1.0 - pos[1] as f64) * (dim.1 as f64 / 2.0);
+    V2f(adj_x, adj_y)
+}
+
+/// A sample of a stroke, which is drawn as a little square (in stroke space)
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Dab {
+    pub center: [f32; 2],
+
+    /// half of the width & height of the square
+    pub radius: [f32; 2],
+
+    /// RGBA, w/o the alpha premultiplied
+    pub color:  [f32; 4],
+}
+
+/// Blends the layer a stroke was drawn on (`src`) onto the strokes below it
+/// (`dst`) w/ premultiplied colors, this is what `graphics::GlRenderer`'s
+/// blending functions work out to.
+pub fn composite(blend: BlendMode, src: [f32; 4], dst: [f32; 4]) -> [f32; 4] {
+    let mut out = [0.0; 4];
+    for channel in 0..3 {
+        let (s, d) = (src[channel], dst[channel]);
+        out[channel] = match blend {
+            BlendMode::Normal   => s + d * (1.0 - src[3]),
+            BlendMode::Multiply => s * d + s * (1.0 - dst[3]) + d * (1.0 - src[3]),
+            BlendMode::Screen   => s + d * (1.0 - s),
+            BlendMode::Add      => (s + d).min(1.0),
+            BlendMode::Erase    => d * (1.0 - src[3]),
+        };
+    }
+
+    out[3] = match blend {
+        BlendMode::Add   => (src[3] + dst[3]).min(1.0),
+        BlendMode::Erase => dst[3] * (1.0 - src[3]),
+        _ => src[3] + dst[3] * (1.0 - src[3]),
+    };
+
+    out
+}
+
+/// Corners of a dab (in units of its radius) in the order they're drawn
+pub static DAB_CORNERS: [[f32; 2]; 6] = [
+    [-1.0,  1.0], [ 1.0,  1.0], [-1.0, -1.0],
+    [-1.0, -1.0], [ 1.0, -1.0], [ 1.0,  1.0],
+];
+
+/// Places each sample of a stroke (at a level of detail, see `lod`) in
+/// stroke space, which the `View` then moves onto the screen.
+pub fn stroke_dabs(stroke: &Stroke, lod: usize, dim: (u32, u32)) -> Vec<Dab> {
+    let (win_x, win_y) = (dim.0 as f32, dim.1 as f32);
+
+    let samples = stroke.lod(lod);
+    let mut dabs = Vec::with_capacity(samples.len());
+    for point in samples {
+        let (wx, wy) = {
+            let adj_x = point.screen_xy.0 as f32 / (win_x / 2.0);
+            let adj_y = point.screen_xy.1 as f32 / (win_y / 2.0);
+
+            let inv_scale = 1.0 / stroke.scale;
+
+            ( ((adj_x - 1.0) * inv_scale), -((adj_y - 1.0) * inv_scale) )
+        };
+
+        // pressure widens the brush ...
+        let fudge_x = DAB_PX / win_x * point.width();
+        let fudge_y = DAB_PX / win_y * point.width();
+
+        // ... and darkens it, unless it's a highlighter
+        let (ink, opacity) = match stroke.brush {
+            BrushMode::Highlighter => (COLOR_HIGHLIGHTER, HIGHLIGHTER_ALPHA),
+            _ => (stroke.ink(), point.opacity()),
+        };
+
+        let color = [ink[0], ink[1], ink[2], opacity * stroke.opacity];
+
+        dabs.push(Dab { center: [wx, wy], radius: [fudge_x, fudge_y], color });
+    }
+
+    dabs
+}
+
+/// Inflates each dab of a stroke to a quad (six verts), for drawing it w/o instancing
+pub fn stroke_verts(stroke: &Stroke, lod: usize, dim: (u32, u32)) -> Vec<Vert2> {
+    stroke_dabs(stroke, lod, dim).iter()
+        .flat_map(|dab| DAB_CORNERS.iter().map(move |corner| Vert2 {
+            pos:   [dab.center[0] + corner[0] * dab.radius[0], dab.center[1] + corner[1] * dab.radius[1], 0.0],
+            color: dab.color,
+        }))
+        .collect()
+}
+
+/// Quads marking each sample of the stroke currently being drawn
+pub fn pending_verts(samples: &[ControlPoint], dim: (u32, u32)) -> Vec<Vert2> {
+    let aspect = dim.1 as f32 / dim.0 as f32;
+
+    let mut verts = Vec::with_capacity(samples.len() * 6);
+    for point in samples {
+        let (wx, wy) = screen_to_unit(V2f(point.screen_xy.0 as f64, point.screen_xy.1 as f64), dim);
+        let size = 0.015 * point.width();
+        let quad = [(-1.0, 1.0), (-1.0, -1.0), (1.0, 1.0), (1.0, 1.0), (1.0, -1.0), (-1.0, -1.0)];
+
+        verts.extend(quad.iter().map(|&(x, y)| Vert2 {
+            pos:   [wx + x * size * aspect, wy + y * size, 0.0],
+            color: COLOR_PENDING,
+        }));
+    }
+
+    verts
+}
+
+/// A little triangle pointing at the upper left of the cursor
+pub fn cursor_verts(cursor: V2f, dim: (u32, u32)) -> Vec<Vert2> {
+    let aspect = dim.1 as f32 / dim.0 as f32;
+    let (wx, wy) = screen_to_unit(cursor, dim);
+    let size = 0.15;
+
+    [(1.0, 0.0), (0.0, 0.0), (0.0, -1.0)].iter().map(|&(x, y)| Vert2 {
+        pos:   [wx + x * size * aspect, wy + y * size, 0.0],
+        color: COLOR_CURSOR,
+    }).collect()
+}
+
+/// Draws the background & every stroke of the canvas, then the minimap, the stroke in progress & the cursor
+pub fn draw_canvas<R: Renderer + ?Sized>(renderer: &mut R, canvas: &Canvas) {
+    renderer.set_antialiasing(canvas.antialiasing);
+    renderer.draw_background(canvas.background, &canvas.view());
+    renderer.draw_strokes(&canvas.strokes, &canvas.view());
+
+    if canvas.minimap {
+        renderer.draw_overlay(&minimap::minimap_verts(canvas, &canvas.minimap_layout()));
+    }
+
+    renderer.draw_overlay(&pending_verts(&canvas.pending, canvas.window_dim));
+    renderer.draw_overlay(&cursor_verts(canvas.cursor(), canvas.window_dim));
+}
+
+/// How long the last frame took, shown on the HUD
+#[derive(Copy, Clone, Debug)]
+pub struct FrameStats {
+    pub frame_time: Duration,
+    pub budget:     Duration,
+
+    /// how long the HUD took to build & draw on the previous frame
+    pub hud_ms: f64,
+}
+
+/// Draws the HUD in the upper right of the screen, `stats` are left off
+/// when there aren't any (i.e: when rendering w/o a window.)
+pub fn draw_hud<R: Renderer + ?Sized>(renderer: &mut R, canvas: &Canvas, stats: Option<&FrameStats>) {
+    let (hue_r, hue_g, hue_b) = canvas.color;
+    let hud_fps = stats.map_or(String::new(), |stats| format!("{}ms", stats.frame_time.as_millis()));
+    let hud_ms  = stats.map_or(String::new(), |stats| format!(" (hud {:.2}ms) ", stats.hud_ms));
+
+    let on_off  = |on: bool| if on { "on" } else { "off" };
+    let scanbox = canvas.view().scanbox;
+    let hud = format!("{}{} paths, {} verts @ ({}, {}) x{:?} {:.0}deg\n\
+                       {:?} / {:?} @ {:.0}%, {} bg, aa {}, snap {}, map {}, ink #{:02x}{:02x}{:02x}, {} = help",
+                      hud_ms, canvas.strokes.len(), canvas.verts(), scanbox.0, scanbox.1, canvas.scale,
+                      canvas.rotation.to_degrees(), canvas.brush, canvas.blend, canvas.opacity * 100.0,
+                      canvas.background.name(), on_off(canvas.antialiasing), on_off(canvas.snapping),
+                      on_off(canvas.minimap), hue_r, hue_g, hue_b, chord_names(canvas.bindings(), Action::ToggleHelp));
+
+    // prompts sit at the bottom of the HUD, while they're open
+    let hud = match canvas.prompt {
+        Some(ref prompt) => format!("{}\n{}: {}_", hud, prompt.label(), prompt.text),
+        None => hud,
+    };
+
+    // frame time turns red once we've blown the frame budget
+    let fps_color = match stats {
+        Some(stats) if stats.frame_time > stats.budget => COLOR_FPS_SLOW,
+        _ => COLOR_FPS,
+    };
+
+    let hud_spans = [
+        Span { text: &hud_fps, color: fps_color.to_rgba() },
+        Span { text: &hud,     color: COLOR_HUD.to_rgba() },
+    ];
+
+    // NOTE: lines are wrapped to fit in the window, w/ a margin on both sides
+    let max_width  = Some(canvas.window_dim.0 as f32 - 2.0 * HUD_MARGIN);
+    let hud_style  = TextStyle { size: HUD_TEXT_SIZE, align: Align::Right, max_width };
+    let hud_layout = text::layout_spans(renderer.font(), &hud_spans, &hud_style);
+    let hud_pos    = hud_layout.anchored(Anchor::TopRight, canvas.window_dim, HUD_MARGIN);
+    let hud_paint  = TextPaint {
+        color:  COLOR_HUD.to_rgba(),
+        shadow: Some(COLOR_HUD_SHADOW.to_rgba()),
+        panel:  Some(COLOR_HUD_PANEL.to_rgba()),
+    };
+
+    // every binding (as the user has them set up) takes the place of the HUD
+    // while the help is open, so the two don't pile up in small windows.
+    if canvas.help {
+        let help = Action::all()
+            .map(|action| format!("{} = {}", chord_names(canvas.bindings(), action), action.name().replace('_', " ")))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let spans  = [Span { text: &help, color: COLOR_HUD.to_rgba() }];
+        let style  = TextStyle { size: HUD_TEXT_SIZE, align: Align::Left, max_width };
+        let layout = text::layout_spans(renderer.font(), &spans, &style);
+        let pos    = layout.anchored(Anchor::TopLeft, canvas.window_dim, HUD_MARGIN);
+        renderer.draw_text(&layout, pos, &hud_paint);
+        return
+    }
+
+    renderer.draw_text(&hud_layout, hud_pos, &hud_paint);
+
+    // bookmarks are listed in the lower left, w/ the one last visited marked
+    if !canvas.bookmarks.is_empty() {
+        let list = canvas.bookmarks.iter().enumerate()
+            .map(|(idx, bookmark)| {
+                let marker = if canvas.bookmark == Some(idx) { ">" } else { " " };
+                format!("{} {}. {}", marker, idx + 1, bookmark.name)
+            })
+            .collect::<Vec<_>>();
+
+        let list   = format!("bookmarks:\n{}", list.join("\n"));
+        let spans  = [Span { text: &list, color: COLOR_HUD.to_rgba() }];
+        let style  = TextStyle { size: HUD_TEXT_SIZE, align: Align::Left, max_width };
+        let layout = text::layout_spans(renderer.font(), &spans, &style);
+        let pos    = layout.anchored(Anchor::BottomLeft, canvas.window_dim, HUD_MARGIN);
+        renderer.draw_text(&layout, pos, &hud_paint);
+    }
+
+}
+
+/// The chords bound to an action, e.g: `Equals/Add`
+fn chord_names(bindings: &Bindings, action: Action) -> String {
+    match bindings.chords(action) {
+        [] => "(unbound)".to_string(),
+        chords => chords.iter().map(|chord| chord.to_string()).collect::<Vec<_>>().join("/"),
+    }
+}
@@ -0,0 +1,249 @@
+//! What koko remembers from one run to the next, kept in `session.toml` in
+//! the config directory (next to `bindings.toml`.)
+//!
+//! NOTE: koko can't save drawings yet, so this stands in for the document:
+//!       the strokes are gone when it exits but the background, bookmarks &
+//!       the blend mode new strokes are drawn w/ aren't.
+
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use dirs;
+use toml::{self, value::Table, Value};
+
+use background::Background;
+use bookmarks::Bookmark;
+use camera::Camera;
+use canvas::{BlendMode, Canvas};
+
+#[derive(Debug)]
+pub enum SessionError {
+    Io(io::Error),
+    Malformed(String),
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SessionError::Io(ref err)        => write!(f, "i/o error reading session: {}", err),
+            SessionError::Malformed(ref msg) => write!(f, "malformed session: {}", msg),
+        }
+    }
+}
+
+impl Error for SessionError {}
+
+impl From<io::Error> for SessionError {
+    fn from(err: io::Error) -> SessionError { SessionError::Io(err) }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Session {
+    pub background: Background,
+    pub blend:      BlendMode,
+    pub bookmarks:  Vec<Bookmark>,
+}
+
+impl Default for Session {
+    fn default() -> Session {
+        Session {
+            background: Background::Solid,
+            blend:      BlendMode::Normal,
+            bookmarks:  vec![],
+        }
+    }
+}
+
+impl Session {
+    /// Takes down whatever's worth keeping from the canvas
+    pub fn of(canvas: &Canvas) -> Session {
+        Session {
+            background: canvas.background,
+            blend:      canvas.blend,
+            bookmarks:  canvas.bookmarks.clone(),
+        }
+    }
+
+    /// Puts the canvas back the way it was when the session was taken
+    pub fn restore(self, canvas: &mut Canvas) {
+        canvas.background = self.background;
+        canvas.blend      = self.blend;
+        canvas.bookmarks  = self.bookmarks;
+        canvas.bookmark   = None;
+    }
+
+    /// Where the last session lives, e.g: `~/.config/koko/session.toml`
+    pub fn user_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("koko").join("session.toml"))
+    }
+
+    /// Loads the last session if there was one, starting over (w/ a
+    /// complaint) if it can't be loaded.
+    pub fn load_user() -> Session {
+        let path = match Session::user_path() {
+            Some(path) => path,
+            None => return Session::default(),
+        };
+
+        if !path.exists() { return Session::default() }
+
+        Session::load(&path).unwrap_or_else(|err| {
+            println!("could not load session @ {:?}: {}", path, err);
+            Session::default()
+        })
+    }
+
+    /// Saves the session for next time, complaining if it can't be.
+    pub fn save_user(&self) {
+        let path = match Session::user_path() {
+            Some(path) => path,
+            None => return,
+        };
+
+        if let Err(err) = self.save(&path) {
+            println!("could not save session @ {:?}: {}", path, err);
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Session, SessionError> {
+        let mut source = String::new();
+        File::open(path)?.read_to_string(&mut source)?;
+        Session::from_toml(&source)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        fs::write(path, self.to_toml())
+    }
+
+    /// Parses a session file, which looks like:
+    ///
+    /// ```toml
+    /// background = "grid"
+    /// blend      = "multiply"
+    ///
+    /// [[bookmarks]]
+    /// name     = "kitchen"
+    /// center   = [10.0, 20.0]
+    /// scale    = 1.0
+    /// rotation = 0.0
+    /// ```
+    ///
+    /// Anything left out is the way koko starts up.
+    pub fn from_toml(source: &str) -> Result<Session, SessionError> {
+        let root = source.parse::<Value>()
+            .map_err(|err| SessionError::Malformed(err.to_string()))?;
+
+        let mut session = Session::default();
+
+        if let Some(background) = root.get("background") {
+            session.background = background.as_str().and_then(Background::from_name)
+                .ok_or_else(|| SessionError::Malformed(format!("unknown background: {}", background)))?;
+        }
+
+        if let Some(blend) = root.get("blend") {
+            session.blend = blend.as_str().and_then(BlendMode::from_name)
+                .ok_or_else(|| SessionError::Malformed(format!("unknown blend mode: {}", blend)))?;
+        }
+
+        if let Some(bookmarks) = root.get("bookmarks") {
+            let bookmarks = bookmarks.as_array()
+                .ok_or_else(|| SessionError::Malformed("`bookmarks` must be a list of tables".to_string()))?;
+
+            session.bookmarks = bookmarks.iter().map(bookmark_from_toml).collect::<Result<Vec<_>, _>>()?;
+        }
+
+        Ok(session)
+    }
+
+    pub fn to_toml(&self) -> String {
+        let mut root = Table::new();
+
+        root.insert("background".to_string(), Value::String(self.background.name().to_string()));
+        root.insert("blend".to_string(),      Value::String(self.blend.name().to_string()));
+        root.insert("bookmarks".to_string(),  Value::Array(self.bookmarks.iter().map(bookmark_to_toml).collect()));
+
+        toml::to_string(&Value::Table(root)).expect("could not write session as toml")
+    }
+}
+
+fn bookmark_from_toml(value: &Value) -> Result<Bookmark, SessionError> {
+    let malformed = |msg: &str| SessionError::Malformed(msg.to_string());
+    let float = |key: &str| value.get(key).and_then(|value| value.as_float()).map(|value| value as f32);
+
+    let name = value.get("name").and_then(|name| name.as_str())
+        .ok_or_else(|| malformed("bookmarks need a `name`"))?;
+
+    let center = match value.get("center").and_then(|center| center.as_array()).map(|center| &center[..]) {
+        Some([x, y]) => match (x.as_float(), y.as_float()) {
+            (Some(x), Some(y)) => [x as f32, y as f32],
+            _ => return Err(malformed("a bookmark's `center` must be a pair of floats")),
+        },
+
+        _ => return Err(malformed("a bookmark's `center` must be a pair of floats")),
+    };
+
+    let home = Camera::home();
+    Ok(Bookmark {
+        name:   name.to_string(),
+        camera: Camera {
+            center,
+            scale:    float("scale").unwrap_or(home.scale),
+            rotation: float("rotation").unwrap_or(home.rotation),
+        },
+    })
+}
+
+fn bookmark_to_toml(bookmark: &Bookmark) -> Value {
+    let camera = bookmark.camera;
+    let mut table = Table::new();
+
+    table.insert("name".to_string(),     Value::String(bookmark.name.clone()));
+    table.insert("center".to_string(),   Value::Array(vec![Value::Float(camera.center[0] as f64), Value::Float(camera.center[1] as f64)]));
+    table.insert("scale".to_string(),    Value::Float(camera.scale as f64));
+    table.insert("rotation".to_string(), Value::Float(camera.rotation as f64));
+
+    Value::Table(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bindings::Bindings;
+
+    #[test]
+    fn sessions_round_trip_through_toml() {
+        let mut canvas = Canvas::new(Bindings::defaults());
+        canvas.background = Background::Isometric;
+        canvas.blend      = BlendMode::Screen;
+        canvas.bookmarks.push(Bookmark { name: "kitchen".to_string(), camera: Camera { center: [10.5, -20.0], scale: 4.0, rotation: 0.5 } });
+        canvas.bookmarks.push(Bookmark { name: "attic \"up top\"".to_string(), camera: Camera::home() });
+
+        let session = Session::of(&canvas);
+        let source  = session.to_toml();
+        assert_eq!(Session::from_toml(&source).expect("could not parse session"), session, "saved as:\n{}", source);
+
+        let mut fresh = Canvas::new(Bindings::defaults());
+        Session::from_toml(&source).unwrap().restore(&mut fresh);
+        assert_eq!((fresh.background, fresh.blend, &fresh.bookmarks), (Background::Isometric, BlendMode::Screen, &canvas.bookmarks));
+    }
+
+    #[test]
+    fn missing_settings_start_over() {
+        assert_eq!(Session::from_toml("").unwrap(), Session::default());
+
+        let session = Session::from_toml("[[bookmarks]]\nname = \"a\"\ncenter = [1.0, 2.0]\n").unwrap();
+        assert_eq!(session.bookmarks[0].camera, Camera { center: [1.0, 2.0], ..Camera::home() });
+
+        assert!(Session::from_toml("[[bookmarks]]\nname = \"a\"\n").is_err());
+        assert!(Session::from_toml("bookmarks = 3").is_err());
+        assert!(Session::from_toml("blend = \"sparkly\"").is_err());
+        assert!(Session::from_toml("background = 1").is_err());
+    }
+}
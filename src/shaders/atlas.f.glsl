@@ -0,0 +1,10 @@
+#version 140
+
+in  vec3 tx_coord;
+out vec4 color;
+
+uniform sampler2DArray page;
+
+void main() {
+    color = texture(page, tx_coord);
+}
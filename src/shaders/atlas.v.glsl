@@ -0,0 +1,11 @@
+#version 140
+
+in  vec2  pos;
+in  vec2  uv;
+in  float layer;  // of the atlas page the tile was drawn into
+out vec3  tx_coord;
+
+void main() {
+    gl_Position = vec4(pos, 0.0, 1.0);
+    tx_coord    = vec3(uv, layer);
+}
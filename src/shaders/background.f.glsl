@@ -0,0 +1,68 @@
+#version 140
+
+out vec4 color;
+
+uniform vec2    dim;
+uniform vec2    ofs;
+uniform float   scale;
+uniform float   rotation; // clockwise on screen, in radians
+
+uniform int     style;    // see `background::Background::shader_style`
+uniform float   grid_px;
+uniform float   major_every;
+uniform float   dot_px;
+uniform float   min_spacing_px;
+
+uniform vec4    paper;
+uniform vec4    minor;
+uniform vec4    major;
+
+// NOTE: this is the same math as `background::coverage`, keep them in sync!
+
+float line_distance(vec2 pos, vec2 normal, float spacing) {
+    float t = dot(pos, normal) / spacing;
+    return abs(t - floor(t + 0.5)) * spacing;
+}
+
+float fade(float spacing_px) {
+    float half_px = min_spacing_px / 2.0;
+    return clamp((spacing_px - half_px) / half_px, 0.0, 1.0);
+}
+
+float line(vec2 pos, vec2 normal, float spacing, float zoom) {
+    return clamp(1.0 - line_distance(pos, normal, spacing) * zoom, 0.0, 1.0);
+}
+
+float coverage_at(vec2 pos, float zoom, float spacing) {
+    if (style == 1) {
+        float dx = line_distance(pos, vec2(1.0, 0.0), spacing);
+        float dy = line_distance(pos, vec2(0.0, 1.0), spacing);
+        return clamp(dot_px + 0.5 - length(vec2(dx, dy)) * zoom, 0.0, 1.0);
+    } else if (style == 2) {
+        return max(line(pos, vec2(1.0, 0.0), spacing, zoom), line(pos, vec2(0.0, 1.0), spacing, zoom));
+    } else if (style == 3) {
+        float height = spacing * sqrt(3.0) / 2.0;
+        return max(line(pos, vec2(1.0, 0.0), height, zoom),
+               max(line(pos, vec2(0.5, 0.8660254), height, zoom),
+                   line(pos, vec2(-0.5, 0.8660254), height, zoom)));
+    }
+
+    return 0.0;
+}
+
+void main() {
+    // undo the view (see `render::View::unapply`) to find this pixel in
+    // the world, w/ a world pixel being a pixel of the screen at zoom 1.0
+    vec2 px = gl_FragCoord.xy - dim / 2.0;
+    vec2 unrotated = vec2(cos(rotation) * px.x - sin(rotation) * px.y,
+                          sin(rotation) * px.x + cos(rotation) * px.y);
+    vec2 world = (unrotated / (dim / 2.0) - ofs) / scale * (dim / 2.0);
+
+    float zoom     = abs(scale);
+    float major_px = grid_px * major_every;
+    float minor_at = coverage_at(world, zoom, grid_px)  * fade(grid_px * zoom);
+    float major_at = coverage_at(world, zoom, major_px) * fade(major_px * zoom);
+
+    vec3 rgb = mix(paper.rgb, minor.rgb, minor.a * minor_at);
+    color    = vec4(mix(rgb, major.rgb, major.a * major_at), 1.0);
+}
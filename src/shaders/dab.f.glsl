@@ -0,0 +1,20 @@
+#version 140
+
+in  vec4 px_color;
+in  vec2 px_local;
+in  vec2 px_half;
+out vec4 color;
+
+uniform bool antialias;
+
+void main() {
+    // signed distance (in pixels) to the edge of the dab, negative inside
+    // of it, the pixel is covered by however much of it is inside.
+    vec2  edge     = abs(px_local) - px_half;
+    float dist     = max(edge.x, edge.y);
+    float coverage = antialias ? clamp(0.5 - dist, 0.0, 1.0) : 1.0;
+
+    // NOTE: strokes are blended together w/ premultiplied alpha
+    float alpha = px_color.a * coverage;
+    color = vec4(px_color.rgb * alpha, alpha);
+}
@@ -0,0 +1,39 @@
+#version 140
+
+in  vec2 corner;    // of the quad, from -1.0 to 1.0
+
+// per instance, i.e: each sample of a stroke
+in  vec2 center;
+in  vec2 radius;
+in  vec4 color;    // w/o premultiplied alpha
+
+out vec4 px_color;
+out vec2 px_local;  // distance from the center of the dab, in pixels
+out vec2 px_half;   // half the size of the dab, in pixels
+
+uniform vec2    dim;
+uniform vec3    ofs;
+uniform vec2    scale;    // x & y can differ, i.e: when filling a tile
+uniform float   rotation; // clockwise on screen, in radians
+uniform bool    antialias;
+
+void main() {
+    // NOTE: antialiased dabs are grown by a pixel on each side, so the
+    //       fragment shader has room to fade out their edges.
+    vec2 half_px  = max(radius * abs(scale) * dim / 2.0, vec2(1e-6));
+    vec2 grown_px = antialias ? half_px + 1.0 : half_px;
+
+    vec2 pos = (center + corner * radius * (grown_px / half_px)) * scale + ofs.xy;
+
+    mat2 rotate = mat2(
+        vec2( cos(rotation), -sin(rotation)),
+        vec2( sin(rotation),  cos(rotation))
+    );
+
+    // spin around the center of the window in pixels, so we don't squash the paths
+    vec2 px_pos = rotate * (pos * dim / 2.0);
+    gl_Position = vec4(px_pos * 2.0 / dim, 0.0, 1.0);
+    px_color    = color;
+    px_local    = corner * grown_px;
+    px_half     = half_px;
+}
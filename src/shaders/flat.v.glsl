@@ -1,12 +1,14 @@
 #version 140
 
 in  vec3 pos;
-in  vec3 color;
+in  vec4 color;
 out vec4 px_color;
 out float fade_factor;
 
+uniform vec2      dim;
 uniform vec3      ofs;
-uniform float   scale;
+uniform vec2    scale;    // x & y can differ, i.e: when filling a tile
+uniform float   rotation; // clockwise on screen, in radians
 
 void main() {
     mat4 translate = mat4(
@@ -17,13 +19,21 @@ void main() {
     );
 
     mat4 scale = mat4(
-        vec4(scale,   0.0,   0.0,  0.0),
-        vec4(  0.0, scale,   0.0,  0.0),
-        vec4(  0.0,   0.0, scale,  0.0),
+        vec4(scale.x,     0.0,     0.0,  0.0),
+        vec4(    0.0, scale.y,     0.0,  0.0),
+        vec4(    0.0,     0.0, scale.x,  0.0),
         vec4(  0.0,   0.0,   0.0,  1.0)
     );
 
-    vec4 pos3d  = vec4(pos, 1.0);
-    gl_Position = translate * scale * pos3d;;
-    px_color    = vec4(color, 1.0);
+    mat2 rotate = mat2(
+        vec2( cos(rotation), -sin(rotation)),
+        vec2( sin(rotation),  cos(rotation))
+    );
+
+    vec4 pos3d  = translate * scale * vec4(pos, 1.0);
+
+    // spin around the center of the window in pixels, so we don't squash the paths
+    vec2 px_pos = rotate * (pos3d.xy * dim / 2.0);
+    gl_Position = vec4(px_pos * 2.0 / dim, pos3d.zw);
+    px_color    = color;
 }
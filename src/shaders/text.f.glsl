@@ -1,11 +1,16 @@
 #version 140
 
 in  vec2 tx_coord;
+in  vec4 px_color;
 out vec4 color;
 
-uniform vec2 c_ofs;
-uniform sampler2DArray atlas_arr;
+uniform sampler2D atlas;
 
 void main() {
-    color = texture(atlas_arr, vec3(tx_coord, c_ofs.y));
+    // panels are drawn in the same batch as glyphs, but don't sample the atlas
+    if (tx_coord.x < 0.0) {
+        color = px_color;
+    } else {
+        color = texture(atlas, tx_coord) * px_color;
+    }
 }
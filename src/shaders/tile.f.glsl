@@ -0,0 +1,10 @@
+#version 140
+
+in  vec2 tx_coord;
+out vec4 color;
+
+uniform sampler2D tile;
+
+void main() {
+    color = texture(tile, tx_coord);
+}
@@ -0,0 +1,10 @@
+#version 140
+
+in  vec2 pos;
+in  vec2 uv;
+out vec2 tx_coord;
+
+void main() {
+    gl_Position = vec4(pos, 0.0, 1.0);
+    tx_coord    = uv;
+}
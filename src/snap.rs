@@ -0,0 +1,125 @@
+//! Snapping the points of a stroke onto the grid, other strokes or angles.
+//!
+//! Everything here is in world pixels (see `background`) so snapping works
+//! the same no matter how the view is panned, zoomed or spun.
+
+use background::{Background, GRID_PX};
+
+/// How close (in pixels on the screen) a point has to be to something to snap to it
+pub static SNAP_PX: f32 = 10.0;
+
+/// Lines drawn w/ shift held are kept to multiples of this angle
+pub static ANGLE_STEP_DEG: f32 = 15.0;
+
+fn distance(lhs: [f32; 2], rhs: [f32; 2]) -> f32 {
+    (rhs[0] - lhs[0]).hypot(rhs[1] - lhs[1])
+}
+
+/// The intersection of grid lines (or dot) nearest to `pos`, if the
+/// background has any.
+pub fn grid_point(background: Background, pos: [f32; 2]) -> Option<[f32; 2]> {
+    match background {
+        Background::Solid => None,
+
+        Background::Dots | Background::Grid => {
+            Some([(pos[0] / GRID_PX).round() * GRID_PX, (pos[1] / GRID_PX).round() * GRID_PX])
+        },
+
+        // NOTE: the corners of the triangles are `k * (height, -GRID_PX / 2) + m * (0, GRID_PX)`
+        //       for whole numbers `k` & `m`, and the nearest one is in one of
+        //       the two columns either side of `pos`.
+        Background::Isometric => {
+            let height = GRID_PX * 3f32.sqrt() / 2.0;
+            let column = (pos[0] / height).floor();
+
+            [column, column + 1.0].iter()
+                .map(|&k| {
+                    let m = ((pos[1] + k * GRID_PX / 2.0) / GRID_PX).round();
+                    [k * height, m * GRID_PX - k * GRID_PX / 2.0]
+                })
+                .min_by(|lhs, rhs| distance(*lhs, pos).total_cmp(&distance(*rhs, pos)))
+        },
+    }
+}
+
+/// Whichever of `targets` is nearest to `pos`, as long as it's w/in `reach`
+pub fn nearest<I: IntoIterator<Item=[f32; 2]>>(targets: I, pos: [f32; 2], reach: f32) -> Option<[f32; 2]> {
+    targets.into_iter()
+        .map(|target| (target, distance(target, pos)))
+        .filter(|&(_, dist)| dist <= reach)
+        .min_by(|lhs, rhs| lhs.1.total_cmp(&rhs.1))
+        .map(|(target, _)| target)
+}
+
+/// The multiple of `ANGLE_STEP_DEG` (in radians) nearest to the direction
+/// from `from` to `pos`, `None` if they're in the same spot.
+pub fn ray_angle(from: [f32; 2], pos: [f32; 2]) -> Option<f32> {
+    let (dx, dy) = (pos[0] - from[0], pos[1] - from[1]);
+    if dx == 0.0 && dy == 0.0 { return None }
+
+    let step = ANGLE_STEP_DEG.to_radians();
+    Some((dy.atan2(dx) / step).round() * step)
+}
+
+/// The point on the ray from `from` (at `angle`, in radians) nearest to `pos`
+pub fn onto_ray(from: [f32; 2], angle: f32, pos: [f32; 2]) -> [f32; 2] {
+    let (sin, cos) = angle.sin_cos();
+    let along = ((pos[0] - from[0]) * cos + (pos[1] - from[1]) * sin).max(0.0);
+    [from[0] + along * cos, from[1] + along * sin]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(lhs: [f32; 2], rhs: [f32; 2]) {
+        assert!(distance(lhs, rhs) < 1e-3, "{:?} != {:?}", lhs, rhs);
+    }
+
+    #[test]
+    fn grids_snap_to_their_intersections() {
+        assert_eq!(grid_point(Background::Solid, [3.0, 4.0]), None);
+        assert_eq!(grid_point(Background::Grid, [GRID_PX + 3.0, -GRID_PX + 4.0]), Some([GRID_PX, -GRID_PX]));
+        assert_eq!(grid_point(Background::Dots, [-3.0, 4.0]), Some([0.0, 0.0]));
+    }
+
+    #[test]
+    fn isometric_grids_snap_to_the_corners_of_triangles() {
+        let height = GRID_PX * 3f32.sqrt() / 2.0;
+
+        // every corner has 6 others around it, a side of a triangle away
+        for idx in 0..6 {
+            let angle  = (30.0 + 60.0 * idx as f32).to_radians();
+            let corner = [GRID_PX * angle.cos(), GRID_PX * angle.sin()];
+            let nudged = [corner[0] + 2.0, corner[1] - 3.0];
+            assert_near(grid_point(Background::Isometric, nudged).unwrap(), corner);
+        }
+
+        assert_near(grid_point(Background::Isometric, [height * 0.45, 1.0]).unwrap(), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn only_nearby_targets_snap() {
+        let targets = vec![[0.0, 0.0], [10.0, 0.0], [50.0, 50.0]];
+        assert_eq!(nearest(targets.clone(), [8.0, 1.0], 5.0), Some([10.0, 0.0]));
+        assert_eq!(nearest(targets, [30.0, 30.0], 5.0), None);
+    }
+
+    #[test]
+    fn angles_snap_to_fifteen_degrees() {
+        assert_eq!(ray_angle([0.0, 0.0], [10.0, 1.0]), Some(0.0));
+        assert_eq!(ray_angle([1.0, 1.0], [1.0, 1.0]), None);
+
+        let degrees = ray_angle([5.0, 5.0], [5.0 + 10.0 * 0.8, 5.0 + 10.0 * 0.6]).unwrap().to_degrees();
+        assert!((degrees - 30.0).abs() < 1e-3, "snapped to {} degrees", degrees);
+    }
+
+    #[test]
+    fn points_land_on_the_ray_nearest_them() {
+        let angle = 90f32.to_radians();
+        assert_near(onto_ray([0.0, 0.0], angle, [3.0, 10.0]), [0.0, 10.0]);
+
+        // points behind the start of the ray land on it
+        assert_near(onto_ray([1.0, 1.0], angle, [4.0, -10.0]), [1.0, 1.0]);
+    }
+}
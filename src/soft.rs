@@ -0,0 +1,431 @@
+use std::io;
+use std::path::Path;
+
+use background::{self, Background};
+use canvas::{BlendMode, Stroke};
+use font::Font;
+use graphics::Vert2;
+use lod;
+use render::{self, Dab, Renderer, View, COLOR_BG, COLOR_GRID_MAJOR, COLOR_GRID_MINOR};
+use text::{self, TextLayout, TextPaint, TextQuad};
+use util;
+
+/// Draws koko on the CPU, into a plain RGBA buffer (top row first.)
+///
+/// This is meant to match `graphics::GlRenderer` closely enough for tests
+/// & exports, not to be fast: triangles are filled wherever they cover the
+/// center of a pixel and text is alpha blended just like the GPU does it.
+/// Antialiased strokes are smoothed like the GPU's shader does and are
+/// layered the same way (see `Renderer::draw_strokes`), but overlays aren't
+/// multisampled.
+pub struct SoftRenderer {
+    font:   Font,
+    dim:    (u32, u32),
+    pixels: Vec<[f32; 4]>,
+
+    antialiasing: bool,
+}
+
+impl SoftRenderer {
+    pub fn new(font: Font) -> SoftRenderer {
+        SoftRenderer { font, dim: (0, 0), pixels: vec![], antialiasing: true }
+    }
+
+    pub fn dim(&self) -> (u32, u32) { self.dim }
+
+    /// The last frame drawn, as 8-bit RGBA pixels top row first
+    pub fn pixels(&self) -> Vec<u8> {
+        self.pixels.iter()
+            .flat_map(|px| px.iter().map(|&channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8))
+            .collect()
+    }
+
+    pub fn save_tga<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        util::save_image_tga(path, &self.pixels(), (self.dim.0 as usize, self.dim.1 as usize))
+    }
+
+    /// Blends `color` over the pixel at (x, y), w/ its alpha scaled by `coverage`
+    fn blend(&mut self, x: i32, y: i32, color: [f32; 4], coverage: f32) {
+        if x < 0 || y < 0 || x >= self.dim.0 as i32 || y >= self.dim.1 as i32 { return }
+
+        let alpha = color[3] * coverage;
+        let dst = &mut self.pixels[y as usize * self.dim.0 as usize + x as usize];
+        for channel in 0..3 {
+            dst[channel] = color[channel] * alpha + dst[channel] * (1.0 - alpha);
+        }
+
+        dst[3] = alpha + dst[3] * (1.0 - alpha);
+    }
+
+    /// Calls `draw(x, y, u, v)` for every pixel whose center is inside `[x, y, w, h]`,
+    /// w/ `u` & `v` being how far across the rectangle the center is.
+    fn each_pixel_in<F: FnMut(&mut Self, i32, i32, f32, f32)>(&mut self, rect: [f32; 4], mut draw: F) {
+        let (x0, y0) = ((rect[0] - 0.5).ceil() as i32, (rect[1] - 0.5).ceil() as i32);
+        let (x1, y1) = ((rect[0] + rect[2] - 0.5).ceil() as i32, (rect[1] + rect[3] - 0.5).ceil() as i32);
+
+        for y in y0.max(0)..y1.min(self.dim.1 as i32) {
+            for x in x0.max(0)..x1.min(self.dim.0 as i32) {
+                let u = (x as f32 + 0.5 - rect[0]) / rect[2];
+                let v = (y as f32 + 0.5 - rect[1]) / rect[3];
+                draw(self, x, y, u, v);
+            }
+        }
+    }
+}
+
+/// Calls `plot(x, y, color, coverage)` for each pixel (w/in `dim`) whose
+/// center is inside of a triangle given in the unit square, w/ its colors
+/// interpolated across it.
+fn fill_triangle<F: FnMut(i32, i32, [f32; 4], f32)>(dim: (u32, u32), tri: [([f32; 2], [f32; 4]); 3], plot: &mut F) {
+    let (width, height) = (dim.0 as f32, dim.1 as f32);
+    let px = tri.iter()
+        .map(|&(pos, _)| ((pos[0] + 1.0) / 2.0 * width, (1.0 - pos[1]) / 2.0 * height))
+        .collect::<Vec<_>>();
+
+    let edge = |a: (f32, f32), b: (f32, f32), x: f32, y: f32| (b.0 - a.0) * (y - a.1) - (b.1 - a.1) * (x - a.0);
+    let area = edge(px[0], px[1], px[2].0, px[2].1);
+    if area == 0.0 { return }
+
+    let min_x = px.iter().fold(f32::MAX, |acc, p| acc.min(p.0)).floor().max(0.0) as i32;
+    let max_x = px.iter().fold(f32::MIN, |acc, p| acc.max(p.0)).ceil().min(width) as i32;
+    let min_y = px.iter().fold(f32::MAX, |acc, p| acc.min(p.1)).floor().max(0.0) as i32;
+    let max_y = px.iter().fold(f32::MIN, |acc, p| acc.max(p.1)).ceil().min(height) as i32;
+
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            let (cx, cy) = (x as f32 + 0.5, y as f32 + 0.5);
+
+            // barycentric weights, all positive (or zero) inside either winding
+            let w0 = edge(px[1], px[2], cx, cy) / area;
+            let w1 = edge(px[2], px[0], cx, cy) / area;
+            let w2 = edge(px[0], px[1], cx, cy) / area;
+            if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 { continue }
+
+            let mix = |channel: usize| tri[0].1[channel] * w0 + tri[1].1[channel] * w1 + tri[2].1[channel] * w2;
+            plot(x, y, [mix(0), mix(1), mix(2), mix(3)], 1.0);
+        }
+    }
+}
+
+fn fill_triangles<I, F>(dim: (u32, u32), verts: I, plot: &mut F)
+    where I: Iterator<Item=([f32; 2], [f32; 4])>, F: FnMut(i32, i32, [f32; 4], f32) {
+    let verts = verts.collect::<Vec<_>>();
+    for tri in verts.chunks(3).filter(|tri| tri.len() == 3) {
+        fill_triangle(dim, [tri[0], tri[1], tri[2]], plot);
+    }
+}
+
+/// Calls `plot` for each pixel (w/in `dim`) a dab covers any of, w/ how much
+/// it covers as the coverage, same as `dab.f.glsl` does.
+fn fill_dab<F: FnMut(i32, i32, [f32; 4], f32)>(dim: (u32, u32), dab: &Dab, view: &View, plot: &mut F) {
+    let (width, height) = (dim.0 as f32, dim.1 as f32);
+    let center = view.apply([dab.center[0], dab.center[1], 0.0]);
+    let (cx, cy) = ((center[0] + 1.0) / 2.0 * width, (1.0 - center[1]) / 2.0 * height);
+    let half = (dab.radius[0] * view.scale.abs() * width / 2.0, dab.radius[1] * view.scale.abs() * height / 2.0);
+
+    // the dab is spun along w/ the view, so look at the pixels around
+    // it w/ that undone.
+    let (sin, cos) = view.rotation.sin_cos();
+    let reach = half.0.hypot(half.1) + 1.0;
+
+    let (min_x, max_x) = (((cx - reach).floor() as i32).max(0), ((cx + reach).ceil() as i32).min(dim.0 as i32));
+    let (min_y, max_y) = (((cy - reach).floor() as i32).max(0), ((cy + reach).ceil() as i32).min(dim.1 as i32));
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+            let (local_x, local_y) = (cos * dx + sin * dy, cos * dy - sin * dx);
+
+            let dist = (local_x.abs() - half.0).max(local_y.abs() - half.1);
+            let coverage = (0.5 - dist).clamp(0.0, 1.0);
+            if coverage > 0.0 { plot(x, y, dab.color, coverage); }
+        }
+    }
+}
+
+impl Renderer for SoftRenderer {
+    fn font(&self) -> &Font { &self.font }
+
+    fn begin_frame(&mut self, dim: (u32, u32), clear: [f32; 4]) {
+        self.dim = dim;
+        self.pixels = vec![clear; dim.0 as usize * dim.1 as usize];
+    }
+
+    fn set_antialiasing(&mut self, enabled: bool) { self.antialiasing = enabled; }
+
+    fn draw_background(&mut self, background: Background, view: &View) {
+        let (paper, minor, major) = (COLOR_BG.to_rgba(), COLOR_GRID_MINOR.to_rgba(), COLOR_GRID_MAJOR.to_rgba());
+        let (width, height) = (self.dim.0 as f32, self.dim.1 as f32);
+
+        for y in 0..self.dim.1 as i32 {
+            for x in 0..self.dim.0 as i32 {
+                let unit  = [(x as f32 + 0.5) / width * 2.0 - 1.0, 1.0 - (y as f32 + 0.5) / height * 2.0];
+                let world = view.unapply_world(unit);
+                let [minor_at, major_at] = background::coverage(background, world, view.scale);
+
+                self.pixels[(y * self.dim.0 as i32 + x) as usize] = paper;
+                self.blend(x, y, minor, minor_at);
+                self.blend(x, y, major, major_at);
+            }
+        }
+    }
+
+    // NOTE: the layer of ink (& each stroke's layer) is premultiplied, just
+    //       like the GPU's tiles. The frame itself isn't, but it's opaque.
+    fn draw_strokes(&mut self, strokes: &[Stroke], view: &View) {
+        let width = self.dim.0 as usize;
+        let mut ink   = vec![[0.0f32; 4]; self.pixels.len()];
+        let mut layer = vec![[0.0f32; 4]; self.pixels.len()];
+
+        for stroke in strokes {
+            // only the part of the layer a stroke touched is blended & cleared
+            let mut touched = (usize::MAX, usize::MAX, 0, 0);
+            {
+                let mut plot = |x: i32, y: i32, color: [f32; 4], coverage: f32| {
+                    let (x, y) = (x as usize, y as usize);
+                    let alpha  = color[3] * coverage;
+                    let src    = [color[0] * alpha, color[1] * alpha, color[2] * alpha, alpha];
+                    for (dst, src) in layer[y * width + x].iter_mut().zip(&src) { *dst = dst.max(*src); }
+
+                    touched = (touched.0.min(x), touched.1.min(y), touched.2.max(x + 1), touched.3.max(y + 1));
+                };
+
+                let lod = lod::level_for(view.scale, stroke.scale);
+                if self.antialiasing {
+                    for dab in render::stroke_dabs(stroke, lod, view.dim) { fill_dab(self.dim, &dab, view, &mut plot); }
+                } else {
+                    let verts = render::stroke_verts(stroke, lod, view.dim);
+                    fill_triangles(self.dim, verts.iter().map(|vert| (view.apply(vert.pos), vert.color)), &mut plot);
+                }
+            }
+
+            for y in touched.1..touched.3 {
+                for x in touched.0..touched.2 {
+                    let idx = y * width + x;
+                    ink[idx]   = render::composite(stroke.blend, layer[idx], ink[idx]);
+                    layer[idx] = [0.0; 4];
+                }
+            }
+        }
+
+        for (dst, src) in self.pixels.iter_mut().zip(&ink) {
+            *dst = render::composite(BlendMode::Normal, *src, *dst);
+        }
+    }
+
+    fn draw_overlay(&mut self, verts: &[Vert2]) {
+        let dim = self.dim;
+        fill_triangles(dim, verts.iter().map(|vert| ([vert.pos[0], vert.pos[1]], vert.color)),
+                       &mut |x, y, color, coverage| self.blend(x, y, color, coverage));
+    }
+
+    fn draw_text(&mut self, layout: &TextLayout, ofs: (f32, f32), paint: &TextPaint) {
+        for quad in text::place_layout(&self.font, layout, ofs, paint) {
+            match quad {
+                TextQuad::Panel(rect, color) => {
+                    self.each_pixel_in(rect, |this, x, y, _, _| this.blend(x, y, color, 1.0));
+                },
+
+                // NOTE: sampled w/ the nearest texel, same as the bitmap atlas on the GPU
+                TextQuad::Bitmap(rect, atlas_rect, color) => {
+                    self.each_pixel_in(rect, |this, x, y, u, v| {
+                        let texel = match this.font {
+                            Font::Bitmap(ref font) => {
+                                let tx = atlas_rect[0] + ((u * atlas_rect[2] as f32) as u32).min(atlas_rect[2] - 1);
+                                let ty = atlas_rect[1] + ((v * atlas_rect[3] as f32) as u32).min(atlas_rect[3] - 1);
+                                let idx = ((ty * font.atlas_dim.0 + tx) * 4) as usize;
+                                [font.atlas[idx], font.atlas[idx + 1], font.atlas[idx + 2], font.atlas[idx + 3]]
+                            },
+
+                            Font::Outline(_) => unreachable!("bitmap glyph placed w/ an outline font"),
+                        };
+
+                        let tint = [
+                            color[0] * texel[0] as f32 / 255.0,
+                            color[1] * texel[1] as f32 / 255.0,
+                            color[2] * texel[2] as f32 / 255.0,
+                            color[3] * texel[3] as f32 / 255.0,
+                        ];
+
+                        this.blend(x, y, tint, 1.0);
+                    });
+                },
+
+                TextQuad::Outline(glyph, color) => {
+                    if let Some(bounds) = glyph.pixel_bounding_box() {
+                        glyph.draw(|gx, gy, coverage| {
+                            self.blend(bounds.min.x + gx as i32, bounds.min.y + gy as i32, color, coverage);
+                        });
+                    }
+                },
+            }
+        }
+    }
+
+    fn end_frame(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use canvas::{BrushMode, ControlPoint};
+    use font;
+    use text::TextStyle;
+    use units::V2;
+
+    // a single, solid, 2x2 glyph for `A`
+    static BLOCK_BDF: &str = "STARTFONT 2.1\n\
+                                      FONTBOUNDINGBOX 2 2 0 0\n\
+                                      STARTCHAR A\nENCODING 65\nDWIDTH 2\nBBX 2 2 0 0\n\
+                                      BITMAP\nC0\nC0\nENDCHAR\n\
+                                      ENDFONT\n";
+
+    fn blank_font() -> Font {
+        Font::Bitmap(font::load_bdf(BLOCK_BDF).expect("test font should parse"))
+    }
+
+    fn pixel(renderer: &SoftRenderer, x: u32, y: u32) -> [u8; 4] {
+        let idx = ((y * renderer.dim().0 + x) * 4) as usize;
+        let pixels = renderer.pixels();
+        [pixels[idx], pixels[idx + 1], pixels[idx + 2], pixels[idx + 3]]
+    }
+
+    #[test]
+    fn clears_the_frame() {
+        let mut renderer = SoftRenderer::new(blank_font());
+        renderer.begin_frame((4, 3), [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(renderer.pixels().len(), 4 * 3 * 4);
+        assert_eq!(pixel(&renderer, 3, 2), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn fills_triangles_in_the_unit_square() {
+        let mut renderer = SoftRenderer::new(blank_font());
+        renderer.begin_frame((4, 4), [0.0, 0.0, 0.0, 1.0]);
+
+        // the upper left half of the screen, the GL's y axis points up
+        let green = [0.0, 1.0, 0.0, 1.0];
+        renderer.draw_overlay(&[
+            Vert2 { pos: [-1.0,  1.0, 0.0], color: green },
+            Vert2 { pos: [ 1.0,  1.0, 0.0], color: green },
+            Vert2 { pos: [-1.0, -1.0, 0.0], color: green },
+        ]);
+
+        assert_eq!(pixel(&renderer, 0, 0), [0, 255, 0, 255]);
+        assert_eq!(pixel(&renderer, 2, 0), [0, 255, 0, 255]);
+        assert_eq!(pixel(&renderer, 3, 3), [0, 0, 0, 255]);
+    }
+
+    fn stroke(brush: BrushMode, blend: BlendMode, opacity: f32, xy: &[(i64, i64)]) -> Stroke {
+        let points = xy.iter().map(|&(x, y)| ControlPoint { screen_xy: V2(x, y), pressure: 1.0, tilt: None }).collect();
+        let mut stroke = Stroke::new((64, 32), 1.0, 0.0, V2(0, 0), points);
+        stroke.brush   = brush;
+        stroke.blend   = blend;
+        stroke.opacity = opacity;
+        stroke
+    }
+
+    /// Draws `strokes` onto a black frame & returns the pixel at (x, y)
+    fn stroked_pixel(strokes: &[Stroke], x: u32, y: u32) -> [u8; 4] {
+        let mut renderer = SoftRenderer::new(blank_font());
+        renderer.begin_frame((64, 32), [0.0, 0.0, 0.0, 1.0]);
+        renderer.draw_strokes(strokes, &View { dim: (64, 32), scale: 1.0, scanbox: V2(0, 0), rotation: 0.0 });
+        pixel(&renderer, x, y)
+    }
+
+    #[test]
+    fn overlapping_dabs_dont_build_up() {
+        let mut renderer = SoftRenderer::new(blank_font());
+        renderer.begin_frame((64, 32), [0.0, 0.0, 0.0, 1.0]);
+
+        let view = View { dim: (64, 32), scale: 1.0, scanbox: V2(0, 0), rotation: 0.0 };
+        renderer.draw_strokes(&[stroke(BrushMode::Normal, BlendMode::Normal, 0.5, &[(20, 16), (22, 16)])], &view);
+
+        // one dab covers (17, 16), both of them cover (21, 16)
+        assert_ne!(pixel(&renderer, 17, 16), [0, 0, 0, 255]);
+        assert_eq!(pixel(&renderer, 21, 16), pixel(&renderer, 17, 16));
+    }
+
+    #[test]
+    fn highlighters_darken_ink_without_hiding_it() {
+        let mut renderer = SoftRenderer::new(blank_font());
+        renderer.begin_frame((64, 32), [0.0, 0.0, 0.0, 1.0]);
+
+        let view    = View { dim: (64, 32), scale: 1.0, scanbox: V2(0, 0), rotation: 0.0 };
+        let strokes = [
+            stroke(BrushMode::Normal,      BlendMode::Normal,   1.0, &[(10, 16), (16, 16)]),
+            stroke(BrushMode::Highlighter, BlendMode::Multiply, 1.0, &[(16, 16), (40, 16)]),
+        ];
+
+        renderer.draw_strokes(&strokes[..1], &view);
+        let before = pixel(&renderer, 14, 16);
+
+        renderer.begin_frame((64, 32), [0.0, 0.0, 0.0, 1.0]);
+        renderer.draw_strokes(&strokes, &view);
+        let after = pixel(&renderer, 14, 16);
+
+        assert!((0..3).all(|channel| after[channel] <= before[channel]) && after != before,
+                "{:?} should be a little darker than {:?}", after, before);
+        assert!(after[0] > 0 || after[2] > 0, "{:?} was covered up", after);
+
+        // ... but they still show up on their own
+        assert_ne!(pixel(&renderer, 40, 16), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn erasers_wipe_back_to_the_background() {
+        let ink    = stroke(BrushMode::Normal, BlendMode::Normal, 1.0, &[(20, 16)]);
+        let eraser = stroke(BrushMode::Normal, BlendMode::Erase,  1.0, &[(22, 16)]);
+
+        let erased = [ink.clone(), eraser.clone()];
+
+        assert_ne!(stroked_pixel(&erased[..1], 21, 16), [0, 0, 0, 255]);
+        assert_eq!(stroked_pixel(&erased, 21, 16), [0, 0, 0, 255]);
+
+        // ... but only the strokes under them
+        assert_ne!(stroked_pixel(&[eraser, ink], 21, 16), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn screen_and_add_lighten() {
+        let under = [stroke(BrushMode::Normal, BlendMode::Normal, 1.0, &[(20, 16)])];
+        let alone = stroked_pixel(&under, 21, 16);
+
+        for &blend in &[BlendMode::Screen, BlendMode::Add] {
+            let over = stroke(BrushMode::Normal, blend, 1.0, &[(22, 16)]);
+            let lit  = stroked_pixel(&[under[0].clone(), over], 21, 16);
+            assert!((0..3).all(|channel| lit[channel] >= alone[channel]) && lit != alone,
+                    "{:?}: {:?} should be lighter than {:?}", blend, lit, alone);
+        }
+    }
+
+    #[test]
+    fn blends_text_panels() {
+        let mut renderer = SoftRenderer::new(blank_font());
+        renderer.begin_frame((8, 8), [0.0, 0.0, 0.0, 1.0]);
+
+        let layout = text::layout(renderer.font(), "", &TextStyle::new(8.0));
+        let paint  = TextPaint { color: [1.0; 4], shadow: None, panel: Some([1.0, 1.0, 1.0, 0.5]) };
+        renderer.draw_text(&layout, (4.0, 4.0), &paint);
+
+        // panels are padded by a quarter of the text size
+        assert_eq!(pixel(&renderer, 1, 1), [0, 0, 0, 255]);
+        assert_eq!(pixel(&renderer, 2, 2), [128, 128, 128, 255]);
+        assert_eq!(pixel(&renderer, 5, 5), [128, 128, 128, 255]);
+        assert_eq!(pixel(&renderer, 6, 6), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn tints_bitmap_glyphs() {
+        let mut renderer = SoftRenderer::new(blank_font());
+        renderer.begin_frame((10, 10), [0.0, 0.0, 0.0, 1.0]);
+
+        // the font is 2px tall, so each texel is scaled up to 4x4
+        let layout = text::layout(renderer.font(), "A", &TextStyle::new(8.0));
+        renderer.draw_text(&layout, (1.0, 1.0), &TextPaint::new([0.0, 0.0, 1.0, 1.0]));
+
+        assert_eq!(pixel(&renderer, 0, 0), [0, 0, 0, 255]);
+        assert_eq!(pixel(&renderer, 1, 1), [0, 0, 255, 255]);
+        assert_eq!(pixel(&renderer, 8, 8), [0, 0, 255, 255]);
+        assert_eq!(pixel(&renderer, 9, 9), [0, 0, 0, 255]);
+    }
+}
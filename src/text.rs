@@ -0,0 +1,309 @@
+use rusttype;
+
+use font::Font;
+
+/// Horizontal alignment of each line within a block of text
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/// Where a block of text is pinned on the screen
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Anchor {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Center,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct TextStyle {
+    /// font size in pixels, see `font::Font`
+    pub size: f32,
+    pub align: Align,
+
+    /// lines longer than this (in pixels) are broken between words
+    pub max_width: Option<f32>,
+}
+
+impl TextStyle {
+    pub fn new(size: f32) -> TextStyle {
+        TextStyle { size, align: Align::Left, max_width: None }
+    }
+}
+
+/// A run of text drawn in a single color, see `layout_spans`
+#[derive(Copy, Clone, Debug)]
+pub struct Span<'a> {
+    pub text:  &'a str,
+    pub color: [f32; 4],
+}
+
+/// How a block of text is colored when it's drawn
+#[derive(Copy, Clone, Debug)]
+pub struct TextPaint {
+    /// color of any characters which weren't given one by a `Span`
+    pub color: [f32; 4],
+
+    /// drops a copy of the text, in this color, just below & right of it
+    pub shadow: Option<[f32; 4]>,
+
+    /// fills a rectangle, in this color, behind the whole block
+    pub panel: Option<[f32; 4]>,
+}
+
+impl TextPaint {
+    pub fn new(color: [f32; 4]) -> TextPaint {
+        TextPaint { color, shadow: None, panel: None }
+    }
+}
+
+/// A single line of a `TextLayout`
+#[derive(Clone, Debug)]
+pub struct Line {
+    pub text:  String,
+    pub width: f32,
+
+    /// offset of the line from the left edge of the block, from alignment
+    pub ofs_x: f32,
+
+    /// color of each character in the line, empty if the text had no spans
+    pub colors: Vec<[f32; 4]>,
+}
+
+/// A block of text which has been broken into lines & measured.
+///
+/// All measurements are in pixels, lines are `line_height` pixels apart.
+#[derive(Clone, Debug)]
+pub struct TextLayout {
+    pub lines:       Vec<Line>,
+    pub size:        f32,
+    pub line_height: f32,
+    pub width:       f32,
+    pub height:      f32,
+}
+
+impl TextLayout {
+    /// Finds the upper left corner of this block when pinned to `anchor` of
+    /// a `dim` sized screen, keeping `margin` pixels away from the edges.
+    pub fn anchored(&self, anchor: Anchor, dim: (u32, u32), margin: f32) -> (f32, f32) {
+        let (win_w, win_h) = (dim.0 as f32, dim.1 as f32);
+
+        match anchor {
+            Anchor::TopLeft     => (margin, margin),
+            Anchor::TopRight    => (win_w - self.width - margin, margin),
+            Anchor::BottomLeft  => (margin, win_h - self.height - margin),
+            Anchor::BottomRight => (win_w - self.width - margin, win_h - self.height - margin),
+            Anchor::Center      => ((win_w - self.width) / 2.0, (win_h - self.height) / 2.0),
+        }
+    }
+}
+
+/// Measures the width (in pixels) of a single line of text.
+pub fn line_width(font: &Font, text: &str, size: f32) -> f32 {
+    let mut width = 0.0;
+    let mut prev  = None;
+    for cp in text.chars() {
+        if let Some(prev) = prev { width += font.kerning(prev, cp, size); }
+        width += font.advance(cp, size);
+        prev   = Some(cp);
+    }
+
+    width
+}
+
+/// Breaks `text` into lines (on newlines, and between words if the style
+/// has a `max_width`) then aligns them w/in the widest line.
+pub fn layout(font: &Font, text: &str, style: &TextStyle) -> TextLayout {
+    layout_colored(font, text, &[], style)
+}
+
+/// Lays out several differently colored spans of text as one block, the
+/// colors follow their characters through any line breaks.
+pub fn layout_spans(font: &Font, spans: &[Span], style: &TextStyle) -> TextLayout {
+    let mut text   = String::new();
+    let mut colors = vec![];
+    for span in spans {
+        text.push_str(span.text);
+        colors.extend(span.text.chars().map(|_| span.color));
+    }
+
+    layout_colored(font, &text, &colors, style)
+}
+
+/// `colors` is either empty, or holds one color for every character of `text`
+fn layout_colored(font: &Font, text: &str, colors: &[[f32; 4]], style: &TextStyle) -> TextLayout {
+    let mut lines = vec![];
+    let mut para_start = 0;
+    for paragraph in text.split('\n') {
+        let mut wrapped = vec![];
+        match style.max_width {
+            Some(max_width) => wrap_paragraph(font, paragraph, style.size, max_width, &mut wrapped),
+            None => wrapped.push(paragraph.to_string()),
+        }
+
+        // wrapping drops the spaces lines were broken on, so find where each
+        // line starts in the paragraph to figure out which colors it gets.
+        let para_chars = paragraph.chars().collect::<Vec<_>>();
+        let mut cursor = 0;
+        for text in wrapped {
+            let len = text.chars().count();
+            while cursor < para_chars.len() && !para_chars[cursor..].iter().cloned().take(len).eq(text.chars()) {
+                cursor += 1;
+            }
+
+            let start  = (para_start + cursor).min(colors.len());
+            let end    = (start + len).min(colors.len());
+            let width  = line_width(font, &text, style.size);
+            lines.push(Line { text, width, ofs_x: 0.0, colors: colors[start..end].to_vec() });
+            cursor += len;
+        }
+
+        para_start += para_chars.len() + 1; // skip the newline
+    }
+
+    let width = lines.iter().fold(0.0, |acc: f32, line| acc.max(line.width));
+    for line in &mut lines {
+        line.ofs_x = match style.align {
+            Align::Left   => 0.0,
+            Align::Center => (width - line.width) / 2.0,
+            Align::Right  => width - line.width,
+        };
+    }
+
+    let line_height = font.line_height(style.size);
+    TextLayout {
+        height: line_height * lines.len() as f32,
+        lines,
+        size:   style.size,
+        line_height,
+        width,
+    }
+}
+
+/// Greedily packs words onto lines no wider than `max_width`, words which
+/// are too long to fit on a line by themselves are split between characters.
+fn wrap_paragraph(font: &Font, paragraph: &str, size: f32, max_width: f32, out: &mut Vec<String>) {
+    let mut line = String::new();
+    for word in paragraph.split(' ') {
+        let candidate = if line.is_empty() { word.to_string() } else { format!("{} {}", line, word) };
+        if line_width(font, &candidate, size) <= max_width {
+            line = candidate;
+            continue;
+        }
+
+        if !line.is_empty() { out.push(line); }
+        line = String::new();
+
+        for cp in word.chars() {
+            line.push(cp);
+            if line.chars().count() > 1 && line_width(font, &line, size) > max_width {
+                line.pop();
+                out.push(line);
+                line = cp.to_string();
+            }
+        }
+    }
+
+    out.push(line);
+}
+
+/// A piece of a block of text, placed on the screen & ready to be drawn.
+///
+/// All rectangles are `[x, y, w, h]` in pixels from the upper left of the screen.
+#[derive(Clone, Debug)]
+pub enum TextQuad {
+    /// a solid rectangle, i.e: a panel behind the text
+    Panel([f32; 4], [f32; 4]),
+
+    /// a glyph from a bitmap font, w/ the rectangle it occupies in the atlas
+    Bitmap([f32; 4], [u32; 4], [f32; 4]),
+
+    /// a glyph from an outline font, rasterized by whoever draws it
+    Outline(rusttype::PositionedGlyph<'static>, [f32; 4]),
+}
+
+/// Places every glyph (plus any panel & shadow) of a block of text w/ its
+/// upper left corner at `ofs`, in the order they should be drawn.
+pub fn place_layout(font: &Font, layout: &TextLayout, ofs: (f32, f32), paint: &TextPaint) -> Vec<TextQuad> {
+    let mut quads = vec![];
+
+    // panels are padded by a fraction of the text size so they don't hug the glyphs
+    if let Some(panel) = paint.panel {
+        let pad = (layout.size / 4.0).round();
+        quads.push(TextQuad::Panel([ofs.0 - pad, ofs.1 - pad, layout.width + 2.0 * pad, layout.height + 2.0 * pad], panel));
+    }
+
+    if let Some(shadow) = paint.shadow {
+        let drop = (layout.size / 12.0).round().max(1.0);
+        for (idx, line) in layout.lines.iter().enumerate() {
+            let line_ofs = (ofs.0 + line.ofs_x + drop, ofs.1 + idx as f32 * layout.line_height + drop);
+            place_line(font, line, layout.size, line_ofs, shadow, true, &mut quads);
+        }
+    }
+
+    for (idx, line) in layout.lines.iter().enumerate() {
+        let line_ofs = (ofs.0 + line.ofs_x, ofs.1 + idx as f32 * layout.line_height);
+        place_line(font, line, layout.size, line_ofs, paint.color, false, &mut quads);
+    }
+
+    quads
+}
+
+/// Positions each glyph of a single line of text, characters w/o a color
+/// of their own are drawn in `color`.
+///
+/// `override_colors` ignores the line's colors entirely, e.g: for shadows.
+///
+/// NOTE: each glyph is placed relative to a "pen" which starts at the
+/// upper left corner of the text and is moved along the line by the
+/// glyph's advance (plus any kerning between it & the previous glyph.)
+///
+/// so essentially a character is:
+///   1. offset from the pen by its bearing
+///   2. sized to its bitmap's dimensions
+///   3. scaled to the user's preferred text size
+///   4. translated to where the user wanted it on the screen (by upper left corner)
+///
+fn place_line(font: &Font, line: &Line, size: f32, ofs: (f32, f32), color: [f32; 4], override_colors: bool, out: &mut Vec<TextQuad>) {
+    let mut pen  = ofs;
+    let mut prev = None;
+    for (idx, cp) in line.text.chars().enumerate() {
+        if let Some(prev) = prev { pen.0 += font.kerning(prev, cp, size); }
+        prev = Some(cp);
+
+        let color = match line.colors.get(idx) {
+            Some(&span_color) if !override_colors => span_color,
+            _ => color,
+        };
+
+        match *font {
+            Font::Bitmap(ref font) => {
+                let scale = size / font.line_height as f32;
+                if let Some(glyph) = font.glyph(cp).filter(|glyph| glyph.size.0 > 0 && glyph.size.1 > 0) {
+                    out.push(TextQuad::Bitmap([
+                        pen.0 + glyph.bearing.0 as f32 * scale,
+                        pen.1 + glyph.bearing.1 as f32 * scale,
+                        glyph.size.0 as f32 * scale,
+                        glyph.size.1 as f32 * scale,
+                    ], [glyph.atlas_xy.0, glyph.atlas_xy.1, glyph.size.0, glyph.size.1], color));
+                }
+            },
+
+            // outlines are positioned by their baseline, rather than the top of the line
+            Font::Outline(ref font) => {
+                let glyph = font.inner.glyph(cp)
+                    .scaled(rusttype::Scale::uniform(size))
+                    .positioned(rusttype::point(pen.0, pen.1 + font.ascent(size)));
+
+                out.push(TextQuad::Outline(glyph, color));
+            },
+        }
+
+        pen.0 += font.advance(cp, size); // move forward one character in textspace
+    }
+}
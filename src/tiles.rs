@@ -0,0 +1,403 @@
+//! Bookkeeping for drawing the canvas as a grid of cached tiles.
+//!
+//! The canvas is cut into tiles which are `TILE_PX` pixels on a side when
+//! seen at their level's zoom (`2^level`), each tile is drawn once into a
+//! texture & then just stretched into place as the view moves. Tiles are
+//! only redrawn when a stroke which touches them is added, so panning
+//! around a busy canvas costs one quad per tile rather than one draw per
+//! stroke.
+//!
+//! NOTE: positions here are in "stroke space", i.e: the unit square the
+//!       paths are in before the view scales, offsets & rotates them (see
+//!       `render::stroke_verts` & `render::View`.)
+
+use std::collections::HashMap;
+
+use canvas::Stroke;
+use render::{self, View};
+
+/// Size of a tile's texture (on each side) in pixels
+pub static TILE_PX: u32 = 256;
+
+/// Zoom levels tiles are drawn at, the view is never more than 2x away from one
+static MIN_LEVEL: i32 = -8;
+static MAX_LEVEL: i32 =  8;
+
+/// Tiles kept around (drawn or known to be empty) before the oldest are forgotten
+pub static MAX_TILES: usize = 512;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TileKey {
+    pub level: i32,
+    pub x: i64,
+    pub y: i64,
+}
+
+impl TileKey {
+    /// The `[min_x, min_y, max_x, max_y]` corner of stroke space this tile covers
+    pub fn bounds(&self, dim: (u32, u32)) -> [f32; 4] {
+        let (tile_w, tile_h) = tile_size(self.level, dim);
+        let (min_x, min_y) = (self.x as f32 * tile_w, self.y as f32 * tile_h);
+        [min_x, min_y, min_x + tile_w, min_y + tile_h]
+    }
+
+    /// `bounds` grown by one of the tile's pixels on each side; dabs are
+    /// antialiased w/ a pixel wide fringe, so strokes this close still touch it.
+    pub fn reach(&self, dim: (u32, u32)) -> [f32; 4] {
+        let bounds = self.bounds(dim);
+        let (px_w, px_h) = ((bounds[2] - bounds[0]) / TILE_PX as f32, (bounds[3] - bounds[1]) / TILE_PX as f32);
+        [bounds[0] - px_w, bounds[1] - px_h, bounds[2] + px_w, bounds[3] + px_h]
+    }
+
+    /// The `(scale, ofs)` which moves this tile's corner of stroke space onto
+    /// the whole unit square, for drawing it into its texture.
+    pub fn projection(&self, dim: (u32, u32)) -> ([f32; 2], [f32; 2]) {
+        let bounds = self.bounds(dim);
+        let scale  = [2.0 / (bounds[2] - bounds[0]), 2.0 / (bounds[3] - bounds[1])];
+        (scale, [-bounds[0] * scale[0] - 1.0, -bounds[1] * scale[1] - 1.0])
+    }
+}
+
+/// The level tiles should be drawn at for a view zoomed to `scale`, rounded
+/// up so tiles are shrunk (rather than blown up) to fit on the screen.
+pub fn level_for(scale: f32) -> i32 {
+    let scale = scale.abs().max(2f32.powi(MIN_LEVEL));
+    (scale.log2().ceil() as i32).clamp(MIN_LEVEL, MAX_LEVEL)
+}
+
+/// Size of a tile at `level` in stroke space, which is stretched to the window
+pub fn tile_size(level: i32, dim: (u32, u32)) -> (f32, f32) {
+    let zoom = 2f32.powi(level);
+    (TILE_PX as f32 / (zoom * dim.0 as f32 / 2.0), TILE_PX as f32 / (zoom * dim.1 as f32 / 2.0))
+}
+
+/// Every tile at least partially on the screen, at the level for `view`
+pub fn visible_tiles(view: &View) -> Vec<TileKey> {
+    let level = level_for(view.scale);
+    let (tile_w, tile_h) = tile_size(level, view.dim);
+
+    // NOTE: the screen is rotated, so find the box around all of its corners
+    let corners = [[-1.0, -1.0], [1.0, -1.0], [1.0, 1.0], [-1.0, 1.0]];
+    let bounds = corners.iter()
+        .map(|&corner| view.unapply(corner))
+        .fold([f32::MAX, f32::MAX, f32::MIN, f32::MIN], |acc, pt| {
+            [acc[0].min(pt[0]), acc[1].min(pt[1]), acc[2].max(pt[0]), acc[3].max(pt[1])]
+        });
+
+    let (min_x, max_x) = ((bounds[0] / tile_w).floor() as i64, (bounds[2] / tile_w).floor() as i64);
+    let (min_y, max_y) = ((bounds[1] / tile_h).floor() as i64, (bounds[3] / tile_h).floor() as i64);
+
+    let mut tiles = vec![];
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            tiles.push(TileKey { level, x, y });
+        }
+    }
+
+    tiles
+}
+
+fn overlaps(lhs: [f32; 4], rhs: [f32; 4]) -> bool {
+    lhs[0] <= rhs[2] && rhs[0] <= lhs[2] && lhs[1] <= rhs[3] && rhs[1] <= lhs[3]
+}
+
+struct Tile<T> {
+    /// whatever the tile was drawn into, `None` if no strokes touch it
+    content:   Option<T>,
+    dirty:     bool,
+    last_used: u64,
+}
+
+/// The tiles which have been drawn so far, & which of them are out of date.
+///
+/// NOTE: strokes are assumed to be added to the end of the list (or all
+///       removed), if there are fewer strokes than last time or the window
+///       changed size every tile is thrown out. the only change made to a
+///       stroke in place is recoloring it, which doesn't move its bounds;
+///       whoever recolors one has to `redraw_stroke` it so its tiles are
+///       drawn again, see `graphics::ControlPath::recolor`.
+pub struct TileCache<T> {
+    dim:    (u32, u32),
+    bounds: Vec<[f32; 4]>,
+    tiles:  HashMap<TileKey, Tile<T>>,
+    frame:  u64,
+}
+
+impl<T> TileCache<T> {
+    pub fn new() -> TileCache<T> {
+        TileCache {
+            dim:    (0, 0),
+            bounds: vec![],
+            tiles:  HashMap::new(),
+            frame:  0,
+        }
+    }
+
+    pub fn len(&self) -> usize { self.tiles.len() }
+
+    pub fn is_empty(&self) -> bool { self.tiles.is_empty() }
+
+    /// Marks every tile as out of date, i.e: when strokes are drawn differently
+    pub fn invalidate(&mut self) {
+        for tile in self.tiles.values_mut() { tile.dirty = true; }
+    }
+
+    /// Catches up w/ the strokes on the canvas, marking any tiles that new
+    /// strokes were drawn on as dirty.
+    pub fn sync(&mut self, strokes: &[Stroke], dim: (u32, u32)) {
+        self.frame += 1;
+
+        if dim != self.dim || strokes.len() < self.bounds.len() {
+            self.dim = dim;
+            self.bounds.clear();
+            self.tiles.clear();
+        }
+
+        // NOTE: strokes reach as far as their widest dab past every sample
+        for stroke in &strokes[self.bounds.len()..] {
+            let dabs   = render::stroke_dabs(stroke, 0, dim);
+            let radius = dabs.iter().fold([0.0f32, 0.0], |acc, dab| [acc[0].max(dab.radius[0]), acc[1].max(dab.radius[1])]);
+            let bounds = dabs.iter()
+                .fold([f32::MAX, f32::MAX, f32::MIN, f32::MIN], |acc, dab| {
+                    [acc[0].min(dab.center[0] - radius[0]), acc[1].min(dab.center[1] - radius[1]),
+                     acc[2].max(dab.center[0] + radius[0]), acc[3].max(dab.center[1] + radius[1])]
+                });
+
+            for (key, tile) in &mut self.tiles {
+                if overlaps(key.reach(dim), bounds) { tile.dirty = true; }
+            }
+
+            self.bounds.push(bounds);
+        }
+    }
+
+    /// Marks the tiles a stroke (by index) touches as dirty, i.e: when it's recolored
+    pub fn redraw_stroke(&mut self, idx: usize) {
+        let (dim, bounds) = match self.bounds.get(idx) {
+            Some(&bounds) => (self.dim, bounds),
+            None => return,
+        };
+
+        for (key, tile) in &mut self.tiles {
+            if overlaps(key.reach(dim), bounds) { tile.dirty = true; }
+        }
+    }
+
+    /// The `[min_x, min_y, max_x, max_y]` corner of stroke space a stroke
+    /// (by index) reaches over, as of the last `sync`
+    pub fn stroke_bounds(&self, idx: usize) -> Option<[f32; 4]> {
+        self.bounds.get(idx).cloned()
+    }
+
+    /// Does `key` need to be drawn (again) before it can be used?
+    pub fn needs_render(&self, key: TileKey) -> bool {
+        self.tiles.get(&key).is_none_or(|tile| tile.dirty)
+    }
+
+    /// The strokes which touch the tile, in the order they should be drawn
+    pub fn strokes_in(&self, key: TileKey) -> Vec<usize> {
+        let tile = key.reach(self.dim);
+        (0..self.bounds.len()).filter(|&idx| overlaps(tile, self.bounds[idx])).collect()
+    }
+
+    pub fn insert(&mut self, key: TileKey, content: Option<T>) {
+        self.tiles.insert(key, Tile { content, dirty: false, last_used: self.frame });
+    }
+
+    /// What the tile was drawn into, if anything, marking it as in use
+    pub fn get(&mut self, key: TileKey) -> Option<&T> {
+        let frame = self.frame;
+        self.tiles.get_mut(&key).and_then(|tile| {
+            tile.last_used = frame;
+            tile.content.as_ref()
+        })
+    }
+
+    /// Whatever every tile that's been drawn so far was drawn into
+    pub fn contents(&self) -> impl Iterator<Item=&T> {
+        self.tiles.values().filter_map(|tile| tile.content.as_ref())
+    }
+
+    /// Forgets the least recently used tiles until there are at most `max`,
+    /// tiles used during this frame are always kept.
+    pub fn evict(&mut self, max: usize) {
+        if self.tiles.len() <= max { return }
+
+        let mut by_age = self.tiles.iter()
+            .filter(|&(_, tile)| tile.last_used < self.frame)
+            .map(|(&key, tile)| (tile.last_used, key))
+            .collect::<Vec<_>>();
+
+        by_age.sort_by_key(|&(last_used, _)| last_used);
+        let excess = self.tiles.len() - max;
+        for (_, key) in by_age.into_iter().take(excess) {
+            self.tiles.remove(&key);
+        }
+    }
+}
+
+impl<T> Default for TileCache<T> {
+    fn default() -> TileCache<T> { TileCache::new() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use canvas::ControlPoint;
+    use units::V2;
+
+    static DIM: (u32, u32) = (1280, 720);
+
+    fn view(scale: f32, scanbox: V2, rotation: f32) -> View {
+        View { dim: DIM, scale, scanbox, rotation }
+    }
+
+    fn dot(x: i64, y: i64) -> Stroke {
+        let point = ControlPoint { screen_xy: V2(x, y), pressure: 1.0, tilt: None };
+        Stroke::new(DIM, 1.0, 0.0, V2(0, 0), vec![point])
+    }
+
+    #[test]
+    fn levels_round_the_zoom_up() {
+        assert_eq!(level_for(1.0), 0);
+        assert_eq!(level_for(1.3), 1);
+        assert_eq!(level_for(0.5), -1);
+        assert_eq!(level_for(0.3), -1);
+        assert_eq!(level_for(-0.3), -1);
+        assert_eq!(level_for(0.0), MIN_LEVEL);
+        assert_eq!(level_for(1000.0), MAX_LEVEL);
+    }
+
+    #[test]
+    fn projection_fills_the_unit_square() {
+        let key = TileKey { level: 1, x: -3, y: 2 };
+        let bounds = key.bounds(DIM);
+        let (scale, ofs) = key.projection(DIM);
+
+        let project = |x: f32, y: f32| [x * scale[0] + ofs[0], y * scale[1] + ofs[1]];
+        let (lower, upper) = (project(bounds[0], bounds[1]), project(bounds[2], bounds[3]));
+        assert!((lower[0] + 1.0).abs() < 1e-4 && (lower[1] + 1.0).abs() < 1e-4, "{:?}", lower);
+        assert!((upper[0] - 1.0).abs() < 1e-4 && (upper[1] - 1.0).abs() < 1e-4, "{:?}", upper);
+    }
+
+    #[test]
+    fn visible_tiles_cover_the_screen() {
+        for &view in &[view(1.0, V2(0, 0), 0.0), view(0.7, V2(300, -90), 0.4), view(3.0, V2(-50, 20), 2.0)] {
+            let tiles = visible_tiles(&view);
+
+            // every spot on the screen lands on one of the tiles
+            for &(x, y) in &[(-1.0, -1.0), (1.0, 1.0), (0.0, 0.0), (-0.9, 0.7), (0.99, -0.99)] {
+                let pt = view.unapply([x, y]);
+                let covered = tiles.iter().any(|key| {
+                    let bounds = key.bounds(DIM);
+                    pt[0] >= bounds[0] && pt[0] <= bounds[2] && pt[1] >= bounds[1] && pt[1] <= bounds[3]
+                });
+
+                assert!(covered, "{:?} isn't covered at {:?}", (x, y), view);
+            }
+        }
+
+        // the level is picked so there aren't too many of them
+        assert_eq!(visible_tiles(&view(1.0, V2(0, 0), 0.0)).len(), 6 * 4);
+        assert!(visible_tiles(&view(1.01, V2(0, 0), 0.0)).len() <= 12 * 8);
+    }
+
+    #[test]
+    fn new_strokes_only_dirty_their_tiles() {
+        let view  = view(1.0, V2(0, 0), 0.0);
+        let tiles = visible_tiles(&view);
+
+        let mut strokes = vec![dot(100, 90)];
+        let mut cache = TileCache::new();
+        cache.sync(&strokes, DIM);
+        for &key in &tiles {
+            assert!(cache.needs_render(key));
+            let content = if cache.strokes_in(key).is_empty() { None } else { Some(key) };
+            cache.insert(key, content);
+        }
+
+        // a stroke in the lower right of the screen
+        strokes.push(dot(1200, 650));
+        cache.sync(&strokes, DIM);
+
+        let dirty = tiles.iter().cloned().filter(|&key| cache.needs_render(key)).collect::<Vec<_>>();
+        assert_eq!(dirty.len(), 1);
+        assert_eq!(cache.strokes_in(dirty[0]), vec![1]);
+        assert!(cache.get(dirty[0]).is_none());
+
+        // & the first stroke's tile is still drawn
+        let drawn = tiles.iter().cloned().filter(|&key| cache.strokes_in(key) == vec![0]).collect::<Vec<_>>();
+        assert_eq!(drawn.len(), 1);
+        assert_eq!(cache.get(drawn[0]), Some(&drawn[0]));
+    }
+
+    #[test]
+    fn strokes_dirty_tiles_their_edges_fade_into() {
+        let view  = view(1.0, V2(0, 0), 0.0);
+        let mut cache = TileCache::<()>::new();
+        cache.sync(&[], DIM);
+        for key in visible_tiles(&view) { cache.insert(key, None); }
+
+        // NOTE: the dab is ~4px wide either side, so its edge stops short of the
+        //       tile to the left but the antialiased fringe around it doesn't.
+        cache.sync(&[dot(900, 260)], DIM);
+
+        let dirty = visible_tiles(&view).into_iter().filter(|&key| cache.needs_render(key)).collect::<Vec<_>>();
+        assert_eq!(dirty, vec![TileKey { level: 0, x: 0, y: 0 }, TileKey { level: 0, x: 1, y: 0 }]);
+        assert_eq!(cache.strokes_in(dirty[0]), vec![0]);
+    }
+
+    #[test]
+    fn recoloring_a_stroke_only_dirties_its_tiles() {
+        let view = view(1.0, V2(0, 0), 0.0);
+        let strokes = [dot(100, 90), dot(1200, 650)];
+
+        let mut cache = TileCache::<()>::new();
+        cache.sync(&strokes, DIM);
+        for key in visible_tiles(&view) { cache.insert(key, None); }
+
+        cache.redraw_stroke(1);
+        cache.redraw_stroke(2);
+
+        let dirty = visible_tiles(&view).into_iter().filter(|&key| cache.needs_render(key)).collect::<Vec<_>>();
+        assert_eq!(dirty.len(), 1);
+        assert_eq!(cache.strokes_in(dirty[0]), vec![1]);
+    }
+
+    #[test]
+    fn erasing_or_resizing_starts_over() {
+        let key = TileKey { level: 0, x: 0, y: 0 };
+        let mut cache = TileCache::new();
+
+        cache.sync(&[dot(10, 10)], DIM);
+        cache.insert(key, Some(()));
+        assert!(!cache.needs_render(key));
+
+        cache.sync(&[], DIM);
+        assert!(cache.needs_render(key));
+
+        cache.insert(key, None);
+        cache.sync(&[], (640, 360));
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn evicts_the_oldest_tiles() {
+        let mut cache = TileCache::new();
+        for x in 0..4 {
+            cache.sync(&[], DIM);
+            cache.insert(TileKey { level: 0, x, y: 0 }, Some(x));
+        }
+
+        // the first tile was just used, so the second is the oldest
+        cache.get(TileKey { level: 0, x: 0, y: 0 });
+        cache.evict(3);
+        assert_eq!(cache.len(), 3);
+        assert!(cache.needs_render(TileKey { level: 0, x: 1, y: 0 }));
+        assert!(!cache.needs_render(TileKey { level: 0, x: 0, y: 0 }));
+
+        // but nothing used this frame is evicted
+        cache.evict(0);
+        assert_eq!(cache.len(), 2);
+    }
+}
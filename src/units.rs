@@ -1,11 +1,24 @@
 use std::ops::{Add, Sub};
 
+#[derive(Copy, Clone, Debug)]
 pub enum Color {
     RGB(u8, u8, u8),
     RGBA(u8, u8, u8, u8),
 }
 
-#[derive(Copy, Clone, Debug)]
+impl Color {
+    /// Converts to normalized RGBA, as expected by shaders
+    pub fn to_rgba(&self) -> [f32; 4] {
+        let (r, g, b, a) = match *self {
+            Color::RGB(r, g, b)     => (r, g, b, 0xFF),
+            Color::RGBA(r, g, b, a) => (r, g, b, a),
+        };
+
+        [r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, a as f32 / 255.0]
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
 pub struct V2f(pub f64, pub f64);
 
 impl V2f {
@@ -23,7 +36,23 @@ impl V2f {
     }
 }
 
-#[derive(Copy, Clone,Debug)]
+impl Add for V2f {
+    type Output = V2f;
+
+    fn add(self, rhs: V2f) -> V2f {
+        V2f(self.0 + rhs.0, self.1 + rhs.1)
+    }
+}
+
+impl Sub for V2f {
+    type Output = V2f;
+
+    fn sub(self, rhs: V2f) -> V2f {
+        V2f(self.0 - rhs.0, self.1 - rhs.1)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct V2(pub i64, pub i64);
 
 impl Add for V2 {
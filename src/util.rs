@@ -1,59 +1,138 @@
 use std::fs::File;
-use std::io::Read;
+use std::io::{self, Read, Write};
 use std::path::Path;
 
-
-// TODO: -> Result<>
-pub fn load_image_tga(path_text: &str) -> (Vec<u8>, (usize,usize)) {
-    let path = Path::new(path_text);
-    let mut file = File::open(path)
-        .expect(&format!("could not find image file @ {:?}", path)[..]);
+/// Loads a 32-bit TGA image (uncompressed or RLE) as RGBA pixels, top row first.
+// NOTE: `+ 0` keeps the byte offsets of the header lined up
+#[allow(clippy::identity_op)]
+pub fn load_image_tga<P: AsRef<Path>>(path: P) -> io::Result<(Vec<u8>, (usize,usize))> {
+    let path = path.as_ref();
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, format!("{:?}: {}", path, msg));
 
     // read file into byte buffer
     let mut buf = vec![];
     let mut ofs = 0;
-    file.read_to_end(&mut buf)
-        .expect("i/o error reading text sprite sheet");
+    File::open(path)?.read_to_end(&mut buf)?;
 
-    assert!(buf[0] == 0); // no id field
-    assert!(buf[1] == 0); // no color map
-    assert!(buf[2] == 2); // uncompressed true color
+    if buf.len() < 18 { return Err(invalid("truncated header")); }
+    if buf[0] != 0 { return Err(invalid("image id fields are not supported")); }
+    if buf[1] != 0 { return Err(invalid("color mapped images are not supported")); }
+    if buf[2] != 2 && buf[2] != 10 { return Err(invalid("only true color images are supported")); }
+    let rle = buf[2] == 10;
     ofs += 3; ofs += 5;   // skip header & color map
 
-    let x_origin = (buf[ofs + 0] as u16) << 8 | buf[ofs + 1] as u16; ofs += 2;
-    let y_origin = (buf[ofs + 0] as u16) << 8 | buf[ofs + 1] as u16; ofs += 2;
+    let _x_origin = (buf[ofs + 1] as u16) << 8 | buf[ofs + 0] as u16; ofs += 2;
+    let _y_origin = (buf[ofs + 1] as u16) << 8 | buf[ofs + 0] as u16; ofs += 2;
 
     let width  = (buf[ofs + 1] as u16) << 8 | buf[ofs + 0] as u16; ofs += 2;
     let height = (buf[ofs + 1] as u16) << 8 | buf[ofs + 0] as u16; ofs += 2;
     let depth  = buf[ofs]; ofs += 1;
     let descriptor = buf[ofs]; ofs += 1;
 
-    println!("x origin: {}, y origin: {}", x_origin, y_origin);
-    println!("bpp: {}, width: {}, height: {}", depth, width, height);
-    println!("descriptor: {:08b}", descriptor);
-
-    println!("reading image data");
     let width  = width as usize;
     let height = height as usize;
     let pitch  = (depth / 8) as usize;
     let size   = width * height * pitch;
-    assert!(pitch == 4);
+    if pitch != 4 { return Err(invalid("only 32bpp images are supported")); }
+
+    // NOTE: unpack run-length encoded images, so both kinds can be read the same way
+    if rle {
+        let packed = buf.split_off(ofs);
+        buf.reserve(size);
+
+        let mut src = 0;
+        while buf.len() < ofs + size {
+            let header = *packed.get(src).ok_or_else(|| invalid("truncated rle packet"))?;
+            let count  = (header & 0x7F) as usize + 1;
+            src += 1;
+
+            if header & 0x80 != 0 {
+                let px = packed.get(src..src + pitch).ok_or_else(|| invalid("truncated rle packet"))?;
+                for _ in 0..count { buf.extend_from_slice(px); }
+                src += pitch;
+            } else {
+                let px = packed.get(src..src + count * pitch).ok_or_else(|| invalid("truncated rle packet"))?;
+                buf.extend_from_slice(px);
+                src += count * pitch;
+            }
+        }
+    }
+
+    if buf.len() < ofs + size { return Err(invalid("truncated image data")); }
+
+    // NOTE: rows are stored bottom-up unless bit 5 of the descriptor is set
+    let top_down = descriptor & 0x20 != 0;
 
     let mut rgba = Vec::with_capacity(size);
     for row in 0..height {
+        let src_row = if top_down { row } else { height - 1 - row };
         for col in 0..width {
-            let px_ofs = (row * width * pitch) + (col * pitch);
-            if buf[ofs + px_ofs + 3] == 0 {
-                rgba.extend_from_slice(&[0x00, 0x00, 0x00, 0xFF]);
-            } else {
-                rgba.push(buf[ofs + px_ofs + 2]);
-                rgba.push(buf[ofs + px_ofs + 1]);
-                rgba.push(buf[ofs + px_ofs + 0]);
-                rgba.push(buf[ofs + px_ofs + 3]);
-            }
+            let px_ofs = ofs + (src_row * width * pitch) + (col * pitch);
+            rgba.push(buf[px_ofs + 2]);
+            rgba.push(buf[px_ofs + 1]);
+            rgba.push(buf[px_ofs + 0]);
+            rgba.push(buf[px_ofs + 3]);
         }
     }
 
     assert!(rgba.len() == width * height * 4);
-    (rgba, (width,height))
+    Ok((rgba, (width,height)))
+}
+
+/// Saves RGBA pixels (top row first) as an uncompressed 32-bit TGA image,
+/// which `load_image_tga` can read back.
+pub fn save_image_tga<P: AsRef<Path>>(path: P, rgba: &[u8], dim: (usize,usize)) -> io::Result<()> {
+    write_image_tga(path.as_ref(), rgba, dim, false)
+}
+
+/// Same as `save_image_tga` but run-length encoded, which is much smaller
+/// for images w/ large flat areas (e.g: mostly empty canvases.)
+pub fn save_image_tga_rle<P: AsRef<Path>>(path: P, rgba: &[u8], dim: (usize,usize)) -> io::Result<()> {
+    write_image_tga(path.as_ref(), rgba, dim, true)
+}
+
+fn write_image_tga(path: &Path, rgba: &[u8], dim: (usize,usize), rle: bool) -> io::Result<()> {
+    let (width, height) = dim;
+    assert!(rgba.len() == width * height * 4);
+    assert!(width <= 0xFFFF && height <= 0xFFFF);
+
+    let mut buf = Vec::with_capacity(18 + rgba.len());
+    buf.extend_from_slice(&[0, 0, if rle { 10 } else { 2 }]); // no id, no color map, true color
+    buf.extend_from_slice(&[0; 5]);          // color map spec
+    buf.extend_from_slice(&[0, 0, 0, 0]);    // x & y origin
+    buf.extend_from_slice(&[width as u8, (width >> 8) as u8, height as u8, (height >> 8) as u8]);
+    buf.push(32);                            // depth
+    buf.push(0x20 | 0x08);                   // top-down, 8 bits of alpha
+
+    // NOTE: pixels are stored as BGRA
+    let bgra = rgba.chunks(4).map(|px| [px[2], px[1], px[0], px[3]]).collect::<Vec<_>>();
+    if !rle {
+        for px in &bgra { buf.extend_from_slice(px); }
+        return File::create(path)?.write_all(&buf);
+    }
+
+    // packets hold up to 128 pixels, either one pixel repeated (w/ the high
+    // bit of the header set) or that many raw pixels. packets may cross rows.
+    let mut start = 0;
+    while start < bgra.len() {
+        let run = bgra[start..].iter().take(128).take_while(|&&px| px == bgra[start]).count();
+        if run > 1 {
+            buf.push(0x80 | (run - 1) as u8);
+            buf.extend_from_slice(&bgra[start]);
+            start += run;
+            continue;
+        }
+
+        // raw pixels continue until the next pair of repeated pixels
+        let mut end = start + 1;
+        while end < bgra.len() && end - start < 128 && (end + 1 == bgra.len() || bgra[end] != bgra[end + 1]) {
+            end += 1;
+        }
+
+        buf.push((end - start - 1) as u8);
+        for px in &bgra[start..end] { buf.extend_from_slice(px); }
+        start = end;
+    }
+
+    File::create(path)?.write_all(&buf)
 }